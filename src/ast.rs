@@ -1,5 +1,23 @@
 use std::fmt::Debug;
 
+/// A byte range `start..end` into the source text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
 pub struct Program {
     pub top_level_decls: Vec<TopLevelDecl>,
 }
@@ -13,8 +31,12 @@ impl Program {
 pub enum TopLevelDecl {
     FunctionDecl(FunctionDecl),
     ExternDecl(ExternDecl),
-    RecordDecl(String, Vec<FieldDecl>),
+    RecordDecl(RecordDecl),
+    EnumDecl(EnumDecl),
     GameDecl(GameDecl),
+    ImportDecl(ImportDecl),
+    InterfaceDecl(InterfaceDecl),
+    ImplDecl(ImplDecl),
 }
 
 impl Debug for TopLevelDecl {
@@ -22,38 +44,78 @@ impl Debug for TopLevelDecl {
         match self {
             TopLevelDecl::FunctionDecl(func) => write!(f, "{:?}", func),
             TopLevelDecl::ExternDecl(extern_decl) => write!(f, "{:?}", extern_decl),
-            TopLevelDecl::RecordDecl(name, fields) => {
-                write!(f, "RecordDecl({}, {:?})", name, fields)
-            }
+            TopLevelDecl::RecordDecl(record_decl) => write!(f, "{:?}", record_decl),
+            TopLevelDecl::EnumDecl(enum_decl) => write!(f, "{:?}", enum_decl),
             TopLevelDecl::GameDecl(game_decl) => write!(f, "{:?}", game_decl),
+            TopLevelDecl::ImportDecl(import_decl) => write!(f, "{:?}", import_decl),
+            TopLevelDecl::InterfaceDecl(interface) => write!(f, "{:?}", interface),
+            TopLevelDecl::ImplDecl(impl_decl) => write!(f, "{:?}", impl_decl),
         }
     }
 }
 
+/// `import std.math;`, which brings the externs of a bundled interface file
+/// into scope.
+pub struct ImportDecl {
+    pub path: String,
+    pub span: Span,
+}
+
+impl ImportDecl {
+    pub fn new(path: String, span: Span) -> Self {
+        ImportDecl { path, span }
+    }
+}
+
+impl Debug for ImportDecl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "import {};", self.path)
+    }
+}
+
 pub struct FunctionDecl {
     pub name: String,
+    /// The names of the type parameters in `fn first<T>(xs: [T]): T?`
+    pub type_params: Vec<String>,
     pub params: Vec<ParamDecl>,
     pub ret: Type,
     pub body: Block,
+    pub span: Span,
 }
 
 impl FunctionDecl {
-    pub fn new(name: String, params: Vec<ParamDecl>, ret: Type, body: Block) -> Self {
+    pub fn new(
+        name: String,
+        type_params: Vec<String>,
+        params: Vec<ParamDecl>,
+        ret: Type,
+        body: Block,
+        span: Span,
+    ) -> Self {
         FunctionDecl {
             name,
+            type_params,
             params,
             ret,
             body,
+            span,
         }
     }
+
+    /// The span of the signature, from `fn` to the return type, which is
+    /// what errors about the declaration point at rather than the body.
+    pub fn signature_span(&self) -> Span {
+        Span::new(self.span.start, self.ret.span.end)
+    }
 }
 
 impl Debug for FunctionDecl {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "fn {}({}): {:?} {:?}",
+            "fn {}{}({}): {:?} {:?}",
             self.name,
+            type_params_to_string(&self.type_params),
             self.params
                 .iter()
                 .map(|p| format!("{:?}", p))
@@ -65,47 +127,111 @@ impl Debug for FunctionDecl {
     }
 }
 
+/// `<A, B>`, or nothing if there are no type parameters.
+fn type_params_to_string(type_params: &[String]) -> String {
+    if type_params.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", type_params.join(", "))
+    }
+}
+
 pub struct ParamDecl {
     pub name: String,
     pub ty: Type,
+    pub span: Span,
 }
 
 impl ParamDecl {
-    pub fn new(name: String, ty: Type) -> Self {
-        ParamDecl { name, ty }
+    pub fn new(name: String, ty: Type, span: Span) -> Self {
+        ParamDecl { name, ty, span }
     }
 }
 
 impl Debug for ParamDecl {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}: {:?}", self.name, self.ty)
+        // the type of `self` is implied
+        if self.name == "self" {
+            write!(f, "self")
+        } else {
+            write!(f, "{}: {:?}", self.name, self.ty)
+        }
     }
 }
 
 #[derive(Clone)]
 pub struct Type {
-    pub name: String,
+    pub kind: TypeKind,
+    pub span: Span,
 }
 
 impl Type {
-    pub fn new(name: String) -> Self {
-        Type { name }
+    pub fn new(kind: TypeKind, span: Span) -> Self {
+        Type { kind, span }
     }
 }
 
 impl Debug for Type {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.name)
+        write!(f, "{:?}", self.kind)
+    }
+}
+
+#[derive(Clone)]
+pub enum TypeKind {
+    /// A built-in type or a record, by name
+    Named(String),
+    /// `Pair<Number, String>`, a generic record with its type arguments
+    Generic(String, Vec<Type>),
+    /// `[T]`
+    Array(Box<Type>),
+    /// `(A, B) -> R`
+    Function(Vec<Type>, Box<Type>),
+    /// `T?`, a `T` or `nil`
+    Optional(Box<Type>),
+}
+
+impl Debug for TypeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeKind::Named(name) => write!(f, "{}", name),
+            TypeKind::Generic(name, args) => write!(
+                f,
+                "{}<{}>",
+                name,
+                args.iter()
+                    .map(|arg| format!("{:?}", arg))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            TypeKind::Array(element) => write!(f, "[{:?}]", element),
+            // `?` after a function type would be part of its return type
+            TypeKind::Optional(inner) if matches!(inner.kind, TypeKind::Function(..)) => {
+                write!(f, "({:?})?", inner)
+            }
+            TypeKind::Optional(inner) => write!(f, "{:?}?", inner),
+            TypeKind::Function(params, ret) => write!(
+                f,
+                "({}) -> {:?}",
+                params
+                    .iter()
+                    .map(|param| format!("{:?}", param))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                ret
+            ),
+        }
     }
 }
 
 pub struct Block {
     pub stmts: Vec<Stmt>,
+    pub span: Span,
 }
 
 impl Block {
-    pub fn new(stmts: Vec<Stmt>) -> Self {
-        Block { stmts }
+    pub fn new(stmts: Vec<Stmt>, span: Span) -> Self {
+        Block { stmts, span }
     }
 }
 
@@ -123,42 +249,242 @@ impl Debug for Block {
     }
 }
 
-pub enum Stmt {
-    Let(String, Type, Expr),
-    Assign(String, Expr),
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Self {
+        Stmt { kind, span }
+    }
+}
+
+impl Debug for Stmt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.kind)
+    }
+}
+
+pub enum StmtKind {
+    /// `let x: Number = 1;`, or `let x = 1;` to take the type of the value
+    Let(String, Option<Type>, Expr),
+    Assign(Expr, Expr),
     If(Expr, Block, Option<Block>),
-    Return(Box<Expr>),
+    While(Expr, Block),
+    /// `for i in start..end { }`
+    ForRange(String, Range, Block),
+    /// `for x in array { }`
+    ForEach(String, Expr, Block),
+    Break,
+    Continue,
+    /// A nested block, which has a scope of its own
+    Block(Block),
+    /// `match shape { Circle(r) => { } _ => { } }`
+    Match(Expr, Vec<MatchArm>),
+    /// `return;` in a function that returns `Void` has no value
+    Return(Option<Box<Expr>>),
     Expr(Expr),
 }
 
-impl Debug for Stmt {
+impl Debug for StmtKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StmtKind::Let(name, Some(ty), expr) => {
+                write!(f, "let {}: {:?} = {:?};", name, ty, expr)
+            }
+            StmtKind::Let(name, None, expr) => write!(f, "let {} = {:?};", name, expr),
+            StmtKind::Assign(target, expr) => write!(f, "{:?} = {:?};", target, expr),
+            StmtKind::If(cond, then, els) => write!(f, "if {:?} {:?} {:?}", cond, then, els),
+            StmtKind::While(cond, body) => write!(f, "while {:?} {:?}", cond, body),
+            StmtKind::ForRange(var, range, body) => {
+                write!(f, "for {} in {:?} {:?}", var, range, body)
+            }
+            StmtKind::ForEach(var, array, body) => {
+                write!(f, "for {} in {:?} {:?}", var, array, body)
+            }
+            StmtKind::Break => write!(f, "break;"),
+            StmtKind::Continue => write!(f, "continue;"),
+            StmtKind::Block(block) => write!(f, "{:?}", block),
+            StmtKind::Match(subject, arms) => write!(
+                f,
+                "match {:?} {{ {} }}",
+                subject,
+                arms.iter()
+                    .map(|arm| format!("{:?}", arm))
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            ),
+            StmtKind::Return(Some(expr)) => write!(f, "return {:?};", expr),
+            StmtKind::Return(None) => write!(f, "return;"),
+            StmtKind::Expr(expr) => write!(f, "{:?}", expr),
+        }
+    }
+}
+
+/// A `pattern => { ... }` arm of a `match`.
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Block,
+    pub span: Span,
+}
+
+impl MatchArm {
+    pub fn new(pattern: Pattern, body: Block, span: Span) -> Self {
+        MatchArm {
+            pattern,
+            body,
+            span,
+        }
+    }
+}
+
+impl Debug for MatchArm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} => {:?}", self.pattern, self.body)
+    }
+}
+
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
+}
+
+impl Pattern {
+    pub fn new(kind: PatternKind, span: Span) -> Self {
+        Pattern { kind, span }
+    }
+}
+
+impl Debug for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.kind)
+    }
+}
+
+pub enum PatternKind {
+    /// A variant, with the fields that are bound to variables of the same
+    /// name in the arm
+    Variant(String, Vec<String>),
+    /// `_`, which matches every variant
+    Wildcard,
+}
+
+impl Debug for PatternKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Stmt::Let(name, ty, expr) => write!(f, "let {}: {:?} = {:?};", name, ty, expr),
-            Stmt::Assign(name, expr) => write!(f, "{} = {:?};", name, expr),
-            Stmt::If(cond, then, els) => write!(f, "if {:?} {:?} {:?}", cond, then, els),
-            Stmt::Return(expr) => write!(f, "return {:?};", expr),
-            Stmt::Expr(expr) => write!(f, "{:?}", expr),
+            PatternKind::Variant(name, fields) if fields.is_empty() => write!(f, "{}", name),
+            PatternKind::Variant(name, fields) => write!(f, "{}({})", name, fields.join(", ")),
+            PatternKind::Wildcard => write!(f, "_"),
+        }
+    }
+}
+
+/// The numbers from `start` up to `end`, which is excluded unless the range
+/// is written `start..=end`.
+pub struct Range {
+    pub start: Expr,
+    pub end: Expr,
+    pub inclusive: bool,
+}
+
+impl Range {
+    pub fn new(start: Expr, end: Expr, inclusive: bool) -> Self {
+        Range {
+            start,
+            end,
+            inclusive,
         }
     }
 }
 
-pub enum Expr {
+impl Debug for Range {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = if self.inclusive { "..=" } else { ".." };
+        write!(f, "{:?}{}{:?}", self.start, op, self.end)
+    }
+}
+
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Expr { kind, span }
+    }
+}
+
+impl Debug for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.kind)
+    }
+}
+
+pub enum ExprKind {
     Number(f64),
     String(String),
+    Bool(bool),
+    Nil,
     Ident(String),
+    UnaryExp(UnaryOp, Box<Expr>),
     BinaryExp(Box<Expr>, Opcode, Box<Expr>),
-    Call(String, Vec<Expr>),
+    /// A call of any expression that is a function, most often a name
+    Call(Box<Expr>, Vec<Expr>),
+    /// `fn(x: Number): Number { ... }`, a function that can use the
+    /// variables around it
+    Lambda(Vec<ParamDecl>, Type, Block),
+    Record(Vec<FieldInit>),
+    Field(Box<Expr>, String),
+    Array(Vec<Expr>),
+    Index(Box<Expr>, Box<Expr>),
 }
 
-impl Debug for Expr {
+impl Debug for ExprKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Expr::Number(n) => write!(f, "{}", n),
-            Expr::String(s) => write!(f, "\"{}\"", s),
-            Expr::Ident(name) => write!(f, "{}", name),
-            Expr::BinaryExp(left, op, right) => write!(f, "({:?} {:?} {:?})", left, op, right),
-            Expr::Call(func, args) => write!(f, "{}({:?})", func, args),
+            ExprKind::Number(n) => write!(f, "{}", n),
+            ExprKind::String(s) => write!(f, "\"{}\"", s),
+            ExprKind::Bool(b) => write!(f, "{}", b),
+            ExprKind::Nil => write!(f, "nil"),
+            ExprKind::Ident(name) => write!(f, "{}", name),
+            ExprKind::UnaryExp(op, operand) => write!(f, "({:?}{:?})", op, operand),
+            ExprKind::BinaryExp(left, op, right) => {
+                write!(f, "({:?} {:?} {:?})", left, op, right)
+            }
+            ExprKind::Call(func, args) => write!(f, "{:?}({:?})", func, args),
+            ExprKind::Lambda(params, ret, body) => write!(
+                f,
+                "fn({}): {:?} {:?}",
+                params
+                    .iter()
+                    .map(|param| format!("{:?}", param))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                ret,
+                body
+            ),
+            ExprKind::Record(fields) => write!(
+                f,
+                "{{ {} }}",
+                fields
+                    .iter()
+                    .map(|field| format!("{:?}", field))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            ExprKind::Field(record, field) => write!(f, "{:?}.{}", record, field),
+            ExprKind::Array(elements) => write!(
+                f,
+                "[{}]",
+                elements
+                    .iter()
+                    .map(|element| format!("{:?}", element))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            ExprKind::Index(array, index) => write!(f, "{:?}[{:?}]", array, index),
         }
     }
 }
@@ -166,14 +492,21 @@ impl Debug for Expr {
 pub enum Opcode {
     Mul,
     Div,
+    Mod,
+    Pow,
     Add,
     Sub,
+    Concat,
     Eq,
     Neq,
     Lt,
     Le,
     Gt,
     Ge,
+    And,
+    Or,
+    /// `a ?? b` is `a`, or `b` if `a` is `nil`
+    Coalesce,
 }
 
 impl Debug for Opcode {
@@ -181,14 +514,37 @@ impl Debug for Opcode {
         match self {
             Opcode::Mul => write!(f, "*"),
             Opcode::Div => write!(f, "/"),
+            Opcode::Mod => write!(f, "%"),
+            Opcode::Pow => write!(f, "^"),
             Opcode::Add => write!(f, "+"),
             Opcode::Sub => write!(f, "-"),
+            Opcode::Concat => write!(f, ".."),
             Opcode::Eq => write!(f, "=="),
             Opcode::Neq => write!(f, "!="),
             Opcode::Lt => write!(f, "<"),
             Opcode::Le => write!(f, "<="),
             Opcode::Gt => write!(f, ">"),
             Opcode::Ge => write!(f, ">="),
+            Opcode::And => write!(f, "&&"),
+            Opcode::Or => write!(f, "||"),
+            Opcode::Coalesce => write!(f, "??"),
+        }
+    }
+}
+
+pub enum UnaryOp {
+    Neg,
+    Not,
+    /// The length of an array or string
+    Len,
+}
+
+impl Debug for UnaryOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnaryOp::Neg => write!(f, "-"),
+            UnaryOp::Not => write!(f, "!"),
+            UnaryOp::Len => write!(f, "#"),
         }
     }
 }
@@ -196,28 +552,274 @@ impl Debug for Opcode {
 pub struct GameDecl {
     pub name: String,
     pub functions: Vec<FunctionDecl>,
+    pub span: Span,
 }
 
 impl GameDecl {
-    pub fn new(name: String, functions: Vec<FunctionDecl>) -> Self {
-        GameDecl { name, functions }
+    pub fn new(name: String, functions: Vec<FunctionDecl>, span: Span) -> Self {
+        GameDecl {
+            name,
+            functions,
+            span,
+        }
     }
 }
 
 impl Debug for GameDecl {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "game {}", self.name)
+        write!(
+            f,
+            "game {} {{ {} }}",
+            self.name,
+            self.functions
+                .iter()
+                .map(|func| format!("{:?}", func))
+                .collect::<Vec<String>>()
+                .join(" ")
+        )
+    }
+}
+
+/// `interface Drawable { fn draw(self) }`, the methods a record needs to be
+/// used as a `Drawable`.
+pub struct InterfaceDecl {
+    pub name: String,
+    pub methods: Vec<MethodSig>,
+    pub span: Span,
+}
+
+impl InterfaceDecl {
+    pub fn new(name: String, methods: Vec<MethodSig>, span: Span) -> Self {
+        InterfaceDecl {
+            name,
+            methods,
+            span,
+        }
+    }
+}
+
+impl Debug for InterfaceDecl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "interface {} {{ {} }}",
+            self.name,
+            self.methods
+                .iter()
+                .map(|method| format!("{:?}", method))
+                .collect::<Vec<String>>()
+                .join(" ")
+        )
+    }
+}
+
+/// A method without a body, as declared by an interface. The first of its
+/// parameters is `self`, of type `Self`.
+pub struct MethodSig {
+    pub name: String,
+    pub params: Vec<ParamDecl>,
+    pub ret: Type,
+    pub span: Span,
+}
+
+impl MethodSig {
+    pub fn new(name: String, params: Vec<ParamDecl>, ret: Type, span: Span) -> Self {
+        MethodSig {
+            name,
+            params,
+            ret,
+            span,
+        }
+    }
+}
+
+impl Debug for MethodSig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "fn {}({}): {:?}",
+            self.name,
+            self.params
+                .iter()
+                .map(|p| format!("{:?}", p))
+                .collect::<Vec<String>>()
+                .join(", "),
+            self.ret
+        )
+    }
+}
+
+/// `impl Player { ... }`, which adds methods to a record, or
+/// `impl Drawable for Player { ... }`, which adds the methods of an
+/// interface. The first parameter of each method is `self`.
+pub struct ImplDecl {
+    pub interface: Option<String>,
+    pub record: String,
+    pub methods: Vec<FunctionDecl>,
+    pub span: Span,
+}
+
+impl ImplDecl {
+    pub fn new(
+        interface: Option<String>,
+        record: String,
+        methods: Vec<FunctionDecl>,
+        span: Span,
+    ) -> Self {
+        ImplDecl {
+            interface,
+            record,
+            methods,
+            span,
+        }
+    }
+}
+
+impl Debug for ImplDecl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "impl ")?;
+        if let Some(interface) = &self.interface {
+            write!(f, "{} for ", interface)?;
+        }
+        write!(
+            f,
+            "{} {{ {} }}",
+            self.record,
+            self.methods
+                .iter()
+                .map(|method| format!("{:?}", method))
+                .collect::<Vec<String>>()
+                .join(" ")
+        )
+    }
+}
+
+pub struct RecordDecl {
+    pub name: String,
+    /// The names of the type parameters in `record Pair<A, B> { ... }`
+    pub type_params: Vec<String>,
+    pub fields: Vec<FieldDecl>,
+    pub span: Span,
+}
+
+impl RecordDecl {
+    pub fn new(name: String, type_params: Vec<String>, fields: Vec<FieldDecl>, span: Span) -> Self {
+        RecordDecl {
+            name,
+            type_params,
+            fields,
+            span,
+        }
+    }
+}
+
+impl Debug for RecordDecl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "record {}{} {{ {} }}",
+            self.name,
+            type_params_to_string(&self.type_params),
+            self.fields
+                .iter()
+                .map(|field| format!("{:?}", field))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    }
+}
+
+/// `enum Shape { Circle(r: Number), Empty }`, a type whose values are one
+/// of several variants, each with fields of its own.
+pub struct EnumDecl {
+    pub name: String,
+    pub variants: Vec<VariantDecl>,
+    pub span: Span,
+}
+
+impl EnumDecl {
+    pub fn new(name: String, variants: Vec<VariantDecl>, span: Span) -> Self {
+        EnumDecl {
+            name,
+            variants,
+            span,
+        }
+    }
+}
+
+impl Debug for EnumDecl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "enum {} {{ {} }}",
+            self.name,
+            self.variants
+                .iter()
+                .map(|variant| format!("{:?}", variant))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    }
+}
+
+pub struct VariantDecl {
+    pub name: String,
+    pub fields: Vec<FieldDecl>,
+    pub span: Span,
+}
+
+impl VariantDecl {
+    pub fn new(name: String, fields: Vec<FieldDecl>, span: Span) -> Self {
+        VariantDecl { name, fields, span }
+    }
+}
+
+impl Debug for VariantDecl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.fields.is_empty() {
+            return write!(f, "{}", self.name);
+        }
+        write!(
+            f,
+            "{}({})",
+            self.name,
+            self.fields
+                .iter()
+                .map(|field| format!("{:?}", field))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    }
+}
+
+/// A `name: value` pair in a record literal.
+pub struct FieldInit {
+    pub name: String,
+    pub value: Expr,
+    pub span: Span,
+}
+
+impl FieldInit {
+    pub fn new(name: String, value: Expr, span: Span) -> Self {
+        FieldInit { name, value, span }
+    }
+}
+
+impl Debug for FieldInit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {:?}", self.name, self.value)
     }
 }
 
 pub struct FieldDecl {
     pub name: String,
     pub ty: Type,
+    pub span: Span,
 }
 
 impl FieldDecl {
-    pub fn new(name: String, ty: Type) -> Self {
-        FieldDecl { name, ty }
+    pub fn new(name: String, ty: Type, span: Span) -> Self {
+        FieldDecl { name, ty, span }
     }
 }
 
@@ -231,11 +833,27 @@ pub struct ExternDecl {
     pub name: String,
     pub params: Vec<ParamDecl>,
     pub ret: Type,
+    /// The Lua function this binds to, such as `love.graphics.rectangle`, if
+    /// it has a different name than the extern
+    pub lua_name: Option<String>,
+    pub span: Span,
 }
 
 impl ExternDecl {
-    pub fn new(name: String, params: Vec<ParamDecl>, ret: Type) -> Self {
-        ExternDecl { name, params, ret }
+    pub fn new(
+        name: String,
+        params: Vec<ParamDecl>,
+        ret: Type,
+        lua_name: Option<String>,
+        span: Span,
+    ) -> Self {
+        ExternDecl {
+            name,
+            params,
+            ret,
+            lua_name,
+            span,
+        }
     }
 }
 
@@ -251,6 +869,10 @@ impl Debug for ExternDecl {
                 .collect::<Vec<String>>()
                 .join("\n"),
             self.ret
-        )
+        )?;
+        if let Some(lua_name) = &self.lua_name {
+            write!(f, " = \"{}\"", lua_name)?;
+        }
+        Ok(())
     }
 }
@@ -1,123 +1,628 @@
+use std::borrow::Cow;
 use std::io::Write;
 
-use crate::ast::{Block, Expr, FunctionDecl, Opcode, Program, Stmt, TopLevelDecl};
+use crate::ast::{
+    Block, EnumDecl, Expr, ExprKind, ExternDecl, FieldInit, FunctionDecl, GameDecl, MatchArm,
+    Opcode, PatternKind, Program, Stmt, StmtKind, TopLevelDecl, UnaryOp,
+};
+use crate::lua::{LUA_KEYWORDS, lua_name};
+use crate::types::TypeInfo;
 
 const INDENT: usize = 4;
 
-pub fn generate_code<W: Write>(writer: &mut W, program: &Program) -> Result<(), std::io::Error> {
+/// Settings for the generated code.
+pub struct Options {
+    /// The global table whose fields the host calls for game callbacks, such
+    /// as `love` for `love.update`.
+    pub host: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            host: "love".to_string(),
+        }
+    }
+}
+
+pub fn generate_code<W: Write>(
+    writer: &mut W,
+    program: &Program,
+    info: &TypeInfo,
+    options: &Options,
+) -> Result<(), std::io::Error> {
+    // Bind renamed externs first, so that they are in scope in every function
     for decl in &program.top_level_decls {
-        generate_declaration(writer, decl, 0)?;
+        if let TopLevelDecl::ExternDecl(ext) = decl {
+            generate_extern(writer, ext)?;
+        }
+    }
+    // Then create the method tables of records with methods, so that the
+    // methods can be added to them wherever their `impl` is
+    let mut tables: Vec<&str> = Vec::new();
+    for decl in &program.top_level_decls {
+        if let TopLevelDecl::ImplDecl(impl_decl) = decl
+            && !tables.contains(&impl_decl.record.as_str())
+        {
+            tables.push(&impl_decl.record);
+            generate_method_table(writer, &impl_decl.record)?;
+        }
+    }
+    for decl in &program.top_level_decls {
+        generate_declaration(writer, info, decl, options)?;
     }
     Ok(())
 }
 
 fn generate_declaration<W: Write>(
     writer: &mut W,
+    info: &TypeInfo,
     decl: &TopLevelDecl,
-    indent: usize,
+    options: &Options,
 ) -> Result<(), std::io::Error> {
     match decl {
-        TopLevelDecl::FunctionDecl(func) => generate_function(writer, func, indent)?,
-        TopLevelDecl::RecordDecl(name, fields) => {}
-        _ => unimplemented!(),
+        TopLevelDecl::FunctionDecl(func) => generate_function(writer, info, func, None, 0)?,
+        TopLevelDecl::RecordDecl(_) => {}
+        TopLevelDecl::EnumDecl(enum_decl) => generate_enum(writer, enum_decl)?,
+        TopLevelDecl::GameDecl(game) => generate_game(writer, info, game, options)?,
+        TopLevelDecl::ImplDecl(impl_decl) => {
+            let table = lua_name(&impl_decl.record);
+            for method in &impl_decl.methods {
+                generate_function(writer, info, method, Some(&table), 0)?;
+            }
+        }
+        // Interfaces only exist in the type checker
+        TopLevelDecl::InterfaceDecl(_) => {}
+        // Externs are bound by `generate_extern`, and imports are resolved
+        // to externs before code generation
+        TopLevelDecl::ExternDecl(_) | TopLevelDecl::ImportDecl(_) => {}
     }
     Ok(())
 }
 
+/// Binds an extern to the Lua function it names. An extern without a Lua
+/// name refers to a global of the same name and needs no code, unless its
+/// name is a Lua keyword.
+fn generate_extern<W: Write>(writer: &mut W, ext: &ExternDecl) -> Result<(), std::io::Error> {
+    match &ext.lua_name {
+        Some(lua_path) => writeln!(writer, "local {} = {}", lua_name(&ext.name), lua_path),
+        None if LUA_KEYWORDS.contains(&ext.name.as_str()) => writeln!(
+            writer,
+            "local {} = _G[\"{}\"]",
+            lua_name(&ext.name),
+            ext.name
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Generates the variants of an enum. A variant is a table with its name in
+/// the `tag` field; one with fields is built by a function of the same name,
+/// and one without is a single shared table.
+fn generate_enum<W: Write>(writer: &mut W, enum_decl: &EnumDecl) -> Result<(), std::io::Error> {
+    for variant in &enum_decl.variants {
+        if variant.fields.is_empty() {
+            writeln!(
+                writer,
+                "{} = {{ tag = \"{}\" }}",
+                lua_name(&variant.name),
+                variant.name
+            )?;
+            continue;
+        }
+        let params: Vec<Cow<'_, str>> = variant.fields.iter().map(|f| lua_name(&f.name)).collect();
+        writeln!(
+            writer,
+            "function {}({})",
+            lua_name(&variant.name),
+            params.join(", ")
+        )?;
+        write!(
+            writer,
+            "{}return {{ tag = \"{}\"",
+            " ".repeat(INDENT),
+            variant.name
+        )?;
+        for (field, param) in variant.fields.iter().zip(&params) {
+            write!(writer, ", {} = {}", field_key(&field.name), param)?;
+        }
+        writeln!(writer, " }}")?;
+        writeln!(writer, "end")?;
+    }
+    Ok(())
+}
+
+/// Generates the table that holds the methods of a record. It is also the
+/// metatable of the values of the record, so that `p:draw()` finds `draw`.
+fn generate_method_table<W: Write>(writer: &mut W, record: &str) -> Result<(), std::io::Error> {
+    let table = lua_name(record);
+    writeln!(writer, "local {} = {{}}", table)?;
+    writeln!(writer, "{}.__index = {}", table, table)
+}
+
+/// Generates a game as a table of its callbacks, each of which is then
+/// registered with the host.
+fn generate_game<W: Write>(
+    writer: &mut W,
+    info: &TypeInfo,
+    game: &GameDecl,
+    options: &Options,
+) -> Result<(), std::io::Error> {
+    let table = lua_name(&game.name);
+    writeln!(writer, "local {} = {{}}", table)?;
+    for func in &game.functions {
+        generate_function(writer, info, func, Some(&table), 0)?;
+    }
+    for func in &game.functions {
+        writeln!(
+            writer,
+            "{}.{} = {}.{}",
+            options.host, func.name, table, func.name
+        )?;
+    }
+    Ok(())
+}
+
+/// Generates a function, as a field of `table` if there is one.
 fn generate_function<W: Write>(
     writer: &mut W,
+    info: &TypeInfo,
     func: &FunctionDecl,
+    table: Option<&str>,
     indent: usize,
 ) -> Result<(), std::io::Error> {
-    write!(writer, "{}function {}(", " ".repeat(indent), func.name)?;
+    write!(writer, "{}function ", " ".repeat(indent))?;
+    match table {
+        // Callback names are never keywords, but method names may be
+        Some(table) => write!(writer, "{}.{}(", table, lua_name(&func.name))?,
+        None => write!(writer, "{}(", lua_name(&func.name))?,
+    }
     for (i, param) in func.params.iter().enumerate() {
         if i > 0 {
             write!(writer, ", ")?;
         }
-        write!(writer, "{}", param.name)?;
+        write!(writer, "{}", lua_name(&param.name))?;
     }
-    write!(writer, ")\n")?;
-    generate_block(writer, &func.body, indent + INDENT)?;
-    write!(writer, "end\n")?;
+    writeln!(writer, ")")?;
+    generate_block(writer, info, &func.body, indent + INDENT, 0)?;
+    writeln!(writer, "{}end", " ".repeat(indent))?;
     Ok(())
 }
 
+/// Generates the statements of a block. `loop_depth` is the number of loops
+/// the block is in, which names the label that `continue` jumps to.
 fn generate_block<W: Write>(
     writer: &mut W,
+    info: &TypeInfo,
     block: &Block,
     indent: usize,
+    loop_depth: usize,
 ) -> Result<(), std::io::Error> {
-    //write!(writer, "{}do\n", " ".repeat(indent))?;
     for stmt in &block.stmts {
-        generate_statement(writer, stmt, indent /*+ INDENT*/)?;
+        generate_statement(writer, info, stmt, indent, loop_depth)?;
+        // Lua rejects statements after a `return`, and they can't run anyway
+        if matches!(
+            stmt.kind,
+            StmtKind::Return(_) | StmtKind::Break | StmtKind::Continue
+        ) {
+            break;
+        }
     }
-    //write!(writer, "{}end\n", " ".repeat(indent))?;
     Ok(())
 }
 
 fn generate_statement<W: Write>(
     writer: &mut W,
+    info: &TypeInfo,
     stmt: &Stmt,
     indent: usize,
+    loop_depth: usize,
 ) -> Result<(), std::io::Error> {
-    match stmt {
-        Stmt::Return(expr) => {
+    match &stmt.kind {
+        StmtKind::Return(Some(expr)) => {
             write!(writer, "{}return ", " ".repeat(indent))?;
-            generate_expression(writer, expr)?;
-            write!(writer, "{}\n", " ".repeat(indent))?;
+            generate_expression(writer, info, expr, indent)?;
+            writeln!(writer)?;
         }
-        Stmt::Let(name, _, expr) => {
-            write!(writer, "{}local {} = ", " ".repeat(indent), name)?;
-            generate_expression(writer, expr)?;
-            write!(writer, "{}\n", " ".repeat(indent))?;
+        StmtKind::Return(None) => writeln!(writer, "{}return", " ".repeat(indent))?,
+        StmtKind::Let(name, _, expr) => {
+            write!(writer, "{}local {} = ", " ".repeat(indent), lua_name(name))?;
+            generate_expression(writer, info, expr, indent)?;
+            writeln!(writer)?;
         }
-        Stmt::Expr(expr) => {
+        StmtKind::Expr(expr) => {
             write!(writer, "{}", " ".repeat(indent))?;
-            generate_expression(writer, expr)?;
-            write!(writer, "\n")?;
+            // Lua only accepts calls as statements; evaluate anything else
+            // into a throwaway local
+            if !matches!(expr.kind, ExprKind::Call(..)) {
+                write!(writer, "local _ = ")?;
+            } else if starts_with_paren(expr) {
+                write!(writer, ";")?;
+            }
+            generate_expression(writer, info, expr, indent)?;
+            writeln!(writer)?;
         }
-        Stmt::If(cond, then_branch, else_branch) => {
+        StmtKind::If(cond, then_branch, else_branch) => {
             write!(writer, "{}if ", " ".repeat(indent))?;
-            generate_expression(writer, cond)?;
-            write!(writer, " then\n")?;
-            generate_block(writer, then_branch, indent + INDENT)?;
-            if let Some(else_branch) = else_branch {
-                write!(writer, "{}else\n", " ".repeat(indent))?;
-                generate_block(writer, else_branch, indent + INDENT)?;
-                write!(writer, "{}end\n", " ".repeat(indent))?;
+            generate_expression(writer, info, cond, indent)?;
+            writeln!(writer, " then")?;
+            generate_block(writer, info, then_branch, indent + INDENT, loop_depth)?;
+            let mut else_branch = else_branch.as_ref();
+            while let Some(branch) = else_branch {
+                // `else if` is parsed as an `else` block holding a single `if`
+                match branch.stmts.as_slice() {
+                    [
+                        Stmt {
+                            kind: StmtKind::If(cond, then_branch, next),
+                            ..
+                        },
+                    ] => {
+                        write!(writer, "{}elseif ", " ".repeat(indent))?;
+                        generate_expression(writer, info, cond, indent)?;
+                        writeln!(writer, " then")?;
+                        generate_block(writer, info, then_branch, indent + INDENT, loop_depth)?;
+                        else_branch = next.as_ref();
+                    }
+                    _ => {
+                        writeln!(writer, "{}else", " ".repeat(indent))?;
+                        generate_block(writer, info, branch, indent + INDENT, loop_depth)?;
+                        else_branch = None;
+                    }
+                }
+            }
+            writeln!(writer, "{}end", " ".repeat(indent))?;
+        }
+        StmtKind::While(cond, body) => {
+            write!(writer, "{}while ", " ".repeat(indent))?;
+            generate_expression(writer, info, cond, indent)?;
+            writeln!(writer, " do")?;
+            generate_loop_body(writer, info, body, indent, loop_depth + 1)?;
+            writeln!(writer, "{}end", " ".repeat(indent))?;
+        }
+        StmtKind::ForRange(var, range, body) => {
+            write!(writer, "{}for {} = ", " ".repeat(indent), lua_name(var))?;
+            generate_expression(writer, info, &range.start, indent)?;
+            write!(writer, ", ")?;
+            generate_expression(writer, info, &range.end, indent)?;
+            // Lua's `for` includes the end
+            if !range.inclusive {
+                write!(writer, " - 1")?;
             }
+            writeln!(writer, " do")?;
+            generate_loop_body(writer, info, body, indent, loop_depth + 1)?;
+            writeln!(writer, "{}end", " ".repeat(indent))?;
         }
-        Stmt::Assign(name, value) => {
-            write!(writer, "{}let {} = ", " ".repeat(indent), name)?;
-            generate_expression(writer, value)?;
-            write!(writer, ";\n")?;
+        StmtKind::ForEach(var, array, body) => {
+            write!(
+                writer,
+                "{}for _, {} in ipairs(",
+                " ".repeat(indent),
+                lua_name(var)
+            )?;
+            generate_expression(writer, info, array, indent)?;
+            writeln!(writer, ") do")?;
+            generate_loop_body(writer, info, body, indent, loop_depth + 1)?;
+            writeln!(writer, "{}end", " ".repeat(indent))?;
+        }
+        StmtKind::Block(block) => {
+            writeln!(writer, "{}do", " ".repeat(indent))?;
+            generate_block(writer, info, block, indent + INDENT, loop_depth)?;
+            writeln!(writer, "{}end", " ".repeat(indent))?;
+        }
+        StmtKind::Match(subject, arms) => {
+            generate_match(writer, info, subject, arms, indent, loop_depth)?
+        }
+        StmtKind::Break => writeln!(writer, "{}break", " ".repeat(indent))?,
+        StmtKind::Continue => {
+            writeln!(writer, "{}goto continue_{}", " ".repeat(indent), loop_depth)?
+        }
+        StmtKind::Assign(target, value) => {
+            write!(writer, "{}", " ".repeat(indent))?;
+            if starts_with_paren(target) {
+                write!(writer, ";")?;
+            }
+            generate_expression(writer, info, target, indent)?;
+            write!(writer, " = ")?;
+            generate_expression(writer, info, value, indent)?;
+            writeln!(writer)?;
         }
     }
     Ok(())
 }
 
-fn generate_expression<W: Write>(writer: &mut W, expr: &Expr) -> Result<(), std::io::Error> {
-    match expr {
-        Expr::Number(n) => write!(writer, "{}", n),
-        Expr::String(s) => write!(writer, "\"{}\"", s),
-        Expr::Ident(ident) => write!(writer, "{}", ident),
-        Expr::BinaryExp(left, op, right) => {
+/// Generates a `match` as an `if` chain on the `tag` of the subject. A
+/// subject other than a variable is evaluated once into a local first; the
+/// local can't clash with the subject itself, which is evaluated before it
+/// is in scope.
+fn generate_match<W: Write>(
+    writer: &mut W,
+    info: &TypeInfo,
+    subject: &Expr,
+    arms: &[MatchArm],
+    indent: usize,
+    loop_depth: usize,
+) -> Result<(), std::io::Error> {
+    let (subject_name, indent) = match &subject.kind {
+        ExprKind::Ident(name) => (lua_name(name), indent),
+        // Any other subject is evaluated once into a local named `match`,
+        // which is a Phobos keyword and so can't hide a variable of the arms
+        _ => {
+            writeln!(writer, "{}do", " ".repeat(indent))?;
+            write!(writer, "{}local match = ", " ".repeat(indent + INDENT))?;
+            generate_expression(writer, info, subject, indent)?;
+            writeln!(writer)?;
+            (Cow::Borrowed("match"), indent + INDENT)
+        }
+    };
+    for (i, arm) in arms.iter().enumerate() {
+        let fields = match &arm.pattern.kind {
+            PatternKind::Variant(name, fields) => {
+                let keyword = if i == 0 { "if" } else { "elseif" };
+                writeln!(
+                    writer,
+                    "{}{} {}.tag == \"{}\" then",
+                    " ".repeat(indent),
+                    keyword,
+                    subject_name,
+                    name
+                )?;
+                fields.as_slice()
+            }
+            PatternKind::Wildcard if i == 0 => {
+                writeln!(writer, "{}do", " ".repeat(indent))?;
+                &[]
+            }
+            PatternKind::Wildcard => {
+                writeln!(writer, "{}else", " ".repeat(indent))?;
+                &[]
+            }
+        };
+        // The fields are read in one statement, so that a binding with the
+        // same name as the subject doesn't hide it from the others
+        if !fields.is_empty() {
+            let names: Vec<Cow<'_, str>> = fields.iter().map(|f| lua_name(f)).collect();
+            let values: Vec<String> = fields
+                .iter()
+                .map(|f| format!("{}{}", subject_name, field_access(f)))
+                .collect();
+            writeln!(
+                writer,
+                "{}local {} = {}",
+                " ".repeat(indent + INDENT),
+                names.join(", "),
+                values.join(", ")
+            )?;
+        }
+        generate_block(writer, info, &arm.body, indent + INDENT, loop_depth)?;
+        // Later arms can't match
+        if let PatternKind::Wildcard = arm.pattern.kind {
+            break;
+        }
+    }
+    if !arms.is_empty() {
+        writeln!(writer, "{}end", " ".repeat(indent))?;
+    }
+    if !matches!(subject.kind, ExprKind::Ident(_)) {
+        writeln!(writer, "{}end", " ".repeat(indent - INDENT))?;
+    }
+    Ok(())
+}
+
+/// Generates the body of a loop. Lua has no `continue`, so if the body
+/// continues it is wrapped in a `do` block followed by a label to jump to.
+/// The `do` block keeps the jump from entering the scope of a local.
+fn generate_loop_body<W: Write>(
+    writer: &mut W,
+    info: &TypeInfo,
+    body: &Block,
+    indent: usize,
+    loop_depth: usize,
+) -> Result<(), std::io::Error> {
+    if !continues(body) {
+        return generate_block(writer, info, body, indent + INDENT, loop_depth);
+    }
+    writeln!(writer, "{}do", " ".repeat(indent + INDENT))?;
+    generate_block(writer, info, body, indent + 2 * INDENT, loop_depth)?;
+    writeln!(writer, "{}end", " ".repeat(indent + INDENT))?;
+    writeln!(
+        writer,
+        "{}::continue_{}::",
+        " ".repeat(indent + INDENT),
+        loop_depth
+    )?;
+    Ok(())
+}
+
+/// Whether a loop body has a `continue` for that loop, rather than for a
+/// loop nested in it.
+fn continues(block: &Block) -> bool {
+    block.stmts.iter().any(|stmt| match &stmt.kind {
+        StmtKind::Continue => true,
+        StmtKind::If(_, then_branch, else_branch) => {
+            continues(then_branch) || else_branch.as_ref().is_some_and(continues)
+        }
+        StmtKind::Block(block) => continues(block),
+        StmtKind::Match(_, arms) => arms.iter().any(|arm| continues(&arm.body)),
+        _ => false,
+    })
+}
+
+/// Generates an expression. `indent` is that of the statement it is in,
+/// which the body of a function expression is indented from.
+fn generate_expression<W: Write>(
+    writer: &mut W,
+    info: &TypeInfo,
+    expr: &Expr,
+    indent: usize,
+) -> Result<(), std::io::Error> {
+    match &expr.kind {
+        ExprKind::Number(n) => write!(writer, "{}", n),
+        ExprKind::String(s) => write!(writer, "\"{}\"", s),
+        ExprKind::Bool(b) => write!(writer, "{}", b),
+        ExprKind::Nil => write!(writer, "nil"),
+        ExprKind::Ident(ident) => write!(writer, "{}", lua_name(ident)),
+        ExprKind::UnaryExp(op, operand) => {
+            write!(writer, "(")?;
+            generate_unary_op(writer, op)?;
+            generate_expression(writer, info, operand, indent)?;
+            write!(writer, ")")
+        }
+        // `a or b` only evaluates `b` when it is needed, and is `a ?? b`
+        // unless `a` may be `false`, which `or` would also replace
+        ExprKind::BinaryExp(left, Opcode::Coalesce, right)
+            if !info.false_defaults.contains(&expr.span) =>
+        {
             write!(writer, "(")?;
-            generate_expression(writer, left)?;
+            generate_expression(writer, info, left, indent)?;
+            write!(writer, " or ")?;
+            generate_expression(writer, info, right, indent)?;
+            write!(writer, ")")
+        }
+        // Otherwise `a` is compared with `nil`, in place if evaluating it
+        // again is harmless
+        ExprKind::BinaryExp(left, Opcode::Coalesce, right) if is_repeatable(left) => {
+            write!(writer, "((")?;
+            generate_expression(writer, info, left, indent)?;
+            write!(writer, " ~= nil and ")?;
+            generate_expression(writer, info, left, indent)?;
+            write!(writer, ") or (")?;
+            generate_expression(writer, info, left, indent)?;
+            write!(writer, " == nil and ")?;
+            generate_expression(writer, info, right, indent)?;
+            write!(writer, "))")
+        }
+        // and if not, in a function it is passed to as `...`, which no name
+        // in `b` can hide
+        ExprKind::BinaryExp(left, Opcode::Coalesce, right) => {
+            write!(writer, "((function(...) if ... == nil then return ")?;
+            generate_expression(writer, info, right, indent)?;
+            write!(writer, " end return ... end)(")?;
+            generate_expression(writer, info, left, indent)?;
+            write!(writer, "))")
+        }
+        ExprKind::BinaryExp(left, op, right) => {
+            write!(writer, "(")?;
+            generate_expression(writer, info, left, indent)?;
             generate_op(writer, op)?;
-            generate_expression(writer, right)?;
+            generate_expression(writer, info, right, indent)?;
             write!(writer, ")")
         }
-        Expr::Call(func, args) => {
-            write!(writer, "{}(", func)?;
+        ExprKind::Call(func, args) => {
+            match &func.kind {
+                ExprKind::Field(receiver, method) if info.method_calls.contains(&expr.span) => {
+                    generate_prefix_expression(writer, info, receiver, indent)?;
+                    write!(writer, ":{}", lua_name(method))?;
+                }
+                _ => generate_prefix_expression(writer, info, func, indent)?,
+            }
+            write!(writer, "(")?;
             for (i, arg) in args.iter().enumerate() {
                 if i > 0 {
                     write!(writer, ", ")?;
                 }
-                generate_expression(writer, arg)?;
+                generate_expression(writer, info, arg, indent)?;
             }
             write!(writer, ")")
         }
+        // The body is generated as that of a function outside of any loop,
+        // since a `continue` can't leave the function
+        ExprKind::Lambda(params, _, body) => {
+            let params: Vec<Cow<'_, str>> = params.iter().map(|p| lua_name(&p.name)).collect();
+            writeln!(writer, "function({})", params.join(", "))?;
+            generate_block(writer, info, body, indent + INDENT, 0)?;
+            write!(writer, "{}end", " ".repeat(indent))
+        }
+        // Values of records with methods have the method table of the
+        // record as their metatable
+        ExprKind::Record(fields) => match info.record_literals.get(&expr.span) {
+            Some(record) => {
+                write!(writer, "setmetatable(")?;
+                generate_record(writer, info, fields, indent)?;
+                write!(writer, ", {})", lua_name(record))
+            }
+            _ => generate_record(writer, info, fields, indent),
+        },
+        ExprKind::Array(elements) => {
+            write!(writer, "{{")?;
+            for (i, element) in elements.iter().enumerate() {
+                if i > 0 {
+                    write!(writer, ", ")?;
+                }
+                generate_expression(writer, info, element, indent)?;
+            }
+            write!(writer, "}}")
+        }
+        // Phobos arrays are 1-based like Lua tables, so indexes are passed
+        // through unchanged
+        ExprKind::Index(array, index) => {
+            generate_prefix_expression(writer, info, array, indent)?;
+            write!(writer, "[")?;
+            generate_expression(writer, info, index, indent)?;
+            write!(writer, "]")
+        }
+        ExprKind::Field(record, field) => {
+            generate_prefix_expression(writer, info, record, indent)?;
+            write!(writer, "{}", field_access(field))
+        }
+    }
+}
+
+fn generate_record<W: Write>(
+    writer: &mut W,
+    info: &TypeInfo,
+    fields: &[FieldInit],
+    indent: usize,
+) -> Result<(), std::io::Error> {
+    if fields.is_empty() {
+        return write!(writer, "{{}}");
+    }
+    write!(writer, "{{ ")?;
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            write!(writer, ", ")?;
+        }
+        write!(writer, "{} = ", field_key(&field.name))?;
+        generate_expression(writer, info, &field.value, indent)?;
+    }
+    write!(writer, " }}")
+}
+
+/// Generates an expression that is indexed or has a field read. Lua only
+/// allows that on names, calls and parenthesized expressions, not literals.
+fn generate_prefix_expression<W: Write>(
+    writer: &mut W,
+    info: &TypeInfo,
+    expr: &Expr,
+    indent: usize,
+) -> Result<(), std::io::Error> {
+    if needs_parens(expr) {
+        write!(writer, "(")?;
+        generate_expression(writer, info, expr, indent)?;
+        write!(writer, ")")
+    } else {
+        generate_expression(writer, info, expr, indent)
+    }
+}
+
+/// Whether an expression has to be parenthesized to be called, indexed or
+/// have a field read.
+fn needs_parens(expr: &Expr) -> bool {
+    matches!(
+        expr.kind,
+        ExprKind::Array(_) | ExprKind::Record(_) | ExprKind::String(_) | ExprKind::Lambda(..)
+    )
+}
+
+/// Whether the Lua code for an expression starts with a parenthesis. As a
+/// statement, Lua would read it as a call of whatever is on the line before,
+/// so it needs a `;` in front.
+fn starts_with_paren(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Call(base, _) | ExprKind::Index(base, _) | ExprKind::Field(base, _) => {
+            needs_parens(base) || starts_with_paren(base)
+        }
+        ExprKind::UnaryExp(..) | ExprKind::BinaryExp(..) => true,
+        _ => false,
     }
 }
 
@@ -127,12 +632,62 @@ fn generate_op<W: Write>(writer: &mut W, op: &Opcode) -> Result<(), std::io::Err
         Opcode::Sub => write!(writer, "-")?,
         Opcode::Mul => write!(writer, "*")?,
         Opcode::Div => write!(writer, "/")?,
+        Opcode::Mod => write!(writer, "%")?,
+        Opcode::Pow => write!(writer, "^")?,
+        // `1..2` would be read by Lua as a malformed number
+        Opcode::Concat => write!(writer, " .. ")?,
         Opcode::Eq => write!(writer, "==")?,
-        Opcode::Neq => write!(writer, "!=")?,
+        Opcode::Neq => write!(writer, "~=")?,
         Opcode::Lt => write!(writer, "<")?,
         Opcode::Le => write!(writer, "<=")?,
         Opcode::Gt => write!(writer, ">")?,
         Opcode::Ge => write!(writer, ">=")?,
+        Opcode::And => write!(writer, " and ")?,
+        Opcode::Or => write!(writer, " or ")?,
+        Opcode::Coalesce => unreachable!("`??` is generated with `or`"),
+    }
+    Ok(())
+}
+
+fn generate_unary_op<W: Write>(writer: &mut W, op: &UnaryOp) -> Result<(), std::io::Error> {
+    match op {
+        UnaryOp::Neg => write!(writer, "-")?,
+        UnaryOp::Not => write!(writer, "not ")?,
+        UnaryOp::Len => write!(writer, "#")?,
     }
     Ok(())
 }
+
+/// The key of a field in a table constructor. Field names are kept as they
+/// are so that Lua code sees the names that were declared, keywords or not.
+fn field_key(field: &str) -> Cow<'_, str> {
+    if LUA_KEYWORDS.contains(&field) {
+        Cow::Owned(format!("[\"{}\"]", field))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// Whether evaluating `expr` more than once has no effect, and costs about
+/// as much as storing it would.
+fn is_repeatable(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Number(_)
+        | ExprKind::String(_)
+        | ExprKind::Bool(_)
+        | ExprKind::Nil
+        | ExprKind::Ident(_) => true,
+        ExprKind::Field(record, _) => is_repeatable(record),
+        ExprKind::Index(array, index) => is_repeatable(array) && is_repeatable(index),
+        _ => false,
+    }
+}
+
+/// Reads a field, as `.name`, or as `["name"]` if the name is a keyword.
+fn field_access(field: &str) -> String {
+    if LUA_KEYWORDS.contains(&field) {
+        format!("[\"{}\"]", field)
+    } else {
+        format!(".{}", field)
+    }
+}
@@ -0,0 +1,201 @@
+use std::fmt::{self, Display};
+
+use crate::ast::Span;
+
+/// Error codes reported by the compiler, printed as `E0003` and so on. Codes
+/// are stable so that tools can match on them instead of on the wording of a
+/// message.
+pub mod codes {
+    pub const UNDEFINED_IDENTIFIER: u16 = 1;
+    pub const UNDEFINED_FUNCTION: u16 = 2;
+    pub const MISMATCHED_TYPES: u16 = 3;
+    pub const INVALID_OPERANDS: u16 = 4;
+    pub const ARGUMENT_COUNT: u16 = 5;
+    pub const NOT_A_FUNCTION: u16 = 6;
+    pub const NON_BOOL_CONDITION: u16 = 7;
+    pub const UNDEFINED_TYPE: u16 = 8;
+    pub const DUPLICATE_DEFINITION: u16 = 9;
+    pub const UNTYPED_RECORD: u16 = 10;
+    pub const UNKNOWN_FIELD: u16 = 11;
+    pub const MISSING_FIELDS: u16 = 12;
+    pub const DUPLICATE_FIELD: u16 = 13;
+    pub const NOT_A_RECORD: u16 = 14;
+    pub const INVALID_ASSIGNMENT: u16 = 15;
+    pub const UNTYPED_ARRAY: u16 = 16;
+    pub const NOT_AN_ARRAY: u16 = 17;
+    pub const UNKNOWN_CALLBACK: u16 = 18;
+    pub const CALLBACK_SIGNATURE: u16 = 19;
+    pub const INVALID_LUA_NAME: u16 = 20;
+    pub const UNKNOWN_MODULE: u16 = 21;
+    pub const OUTSIDE_OF_LOOP: u16 = 22;
+    pub const REDECLARATION: u16 = 23;
+    pub const MISSING_RETURN: u16 = 24;
+    pub const UNREACHABLE_CODE: u16 = 25;
+    pub const VOID_VALUE: u16 = 26;
+    pub const NON_EXHAUSTIVE_MATCH: u16 = 27;
+    pub const UNKNOWN_VARIANT: u16 = 28;
+    pub const TYPE_ARGUMENTS: u16 = 29;
+    pub const CANNOT_INFER: u16 = 30;
+    pub const UNKNOWN_METHOD: u16 = 31;
+    pub const MISSING_METHODS: u16 = 32;
+    pub const METHOD_VALUE: u16 = 33;
+    pub const OPTIONAL_ELEMENT: u16 = 34;
+
+    pub const UNEXPECTED_TOKEN: u16 = 100;
+    pub const UNEXPECTED_EOF: u16 = 101;
+    pub const INVALID_TOKEN: u16 = 102;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A message attached to a span of the source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A problem found in a program, pointing at the source that caused it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: u16,
+    pub message: String,
+    /// The primary location; `label` is printed next to its carets.
+    pub span: Span,
+    pub label: String,
+    /// Secondary locations that help explain the problem.
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: u16, message: String, span: Span) -> Self {
+        Diagnostic {
+            severity,
+            code,
+            message,
+            span,
+            label: String::new(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn error(code: u16, message: String, span: Span) -> Self {
+        Diagnostic::new(Severity::Error, code, message, span)
+    }
+
+    pub fn warning(code: u16, message: String, span: Span) -> Self {
+        Diagnostic::new(Severity::Warning, code, message, span)
+    }
+
+    pub fn with_label(mut self, label: String) -> Self {
+        self.label = label;
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: String) -> Self {
+        self.labels.push(Label { span, message });
+        self
+    }
+
+    pub fn with_note(mut self, note: String) -> Self {
+        self.notes.push(note);
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Renders the diagnostic in the style of rustc, quoting the source lines
+    /// it refers to and underlining the spans.
+    pub fn render(&self, file_name: &str, source: &str) -> String {
+        let mut out = format!("{}[E{:04}]: {}\n", self.severity, self.code, self.message);
+        let (line, col) = byte_offset_to_line_col(source, self.span.start);
+
+        // The primary span is underlined with `^`, secondary spans with `-`.
+        let mut marks = vec![(self.span, '^', self.label.as_str())];
+        for label in &self.labels {
+            marks.push((label.span, '-', label.message.as_str()));
+        }
+        marks.sort_by_key(|(span, _, _)| span.start);
+
+        let last_line = marks
+            .iter()
+            .map(|(span, _, _)| byte_offset_to_line_col(source, span.start).0)
+            .max()
+            .unwrap_or(line);
+        let width = last_line.to_string().len();
+        let gutter = " ".repeat(width);
+
+        out.push_str(&format!("{}--> {}:{}:{}\n", gutter, file_name, line, col));
+        out.push_str(&format!("{} |\n", gutter));
+        let mut previous_line = None;
+        for (span, mark, message) in marks {
+            let (line, col) = byte_offset_to_line_col(source, span.start);
+            let text = source.lines().nth(line - 1).unwrap_or("");
+            // Quote each source line once, with every mark on it underneath
+            if previous_line != Some(line) {
+                out.push_str(&format!("{:>width$} | {}\n", line, text, width = width));
+                previous_line = Some(line);
+            }
+            // Underline up to the end of the span or of the line, whichever is first
+            let available = text.chars().count().saturating_sub(col - 1);
+            let length = source
+                .get(span.start..span.end)
+                .map(|s| s.chars().count())
+                .unwrap_or(0)
+                .min(available)
+                .max(1);
+            let underline = format!("{}{}", " ".repeat(col - 1), mark.to_string().repeat(length));
+            if message.is_empty() {
+                out.push_str(&format!("{} | {}\n", gutter, underline));
+            } else {
+                out.push_str(&format!("{} | {} {}\n", gutter, underline, message));
+            }
+        }
+        if !self.notes.is_empty() {
+            out.push_str(&format!("{} |\n", gutter));
+        }
+        for note in &self.notes {
+            out.push_str(&format!("{} = note: {}\n", gutter, note));
+        }
+        out
+    }
+}
+
+pub fn byte_offset_to_line_col(source: &str, offset: usize) -> (usize, usize) {
+    let mut line = 1;
+    let mut col = 1;
+    let mut i = 0;
+
+    for ch in source.chars() {
+        if i == offset {
+            break;
+        }
+        if ch == '\n' {
+            line += 1;
+            col = 1;
+        } else {
+            col += 1;
+        }
+        i += ch.len_utf8();
+    }
+
+    (line, col)
+}
@@ -0,0 +1,90 @@
+use crate::ast::{Program, TopLevelDecl};
+use crate::diagnostic::{Diagnostic, codes};
+use crate::parser;
+
+/// The interface files bundled with the compiler, by import path. They hold
+/// `extern` declarations for the Lua standard library and for LÖVE. Names
+/// are prefixed with their module, so `math.sin` is `math_sin`.
+const INTERFACES: &[(&str, &str)] = &[
+    ("std.base", include_str!("interfaces/std/base.phoi")),
+    ("std.math", include_str!("interfaces/std/math.phoi")),
+    ("std.os", include_str!("interfaces/std/os.phoi")),
+    ("std.string", include_str!("interfaces/std/string.phoi")),
+    ("love.event", include_str!("interfaces/love/event.phoi")),
+    (
+        "love.graphics",
+        include_str!("interfaces/love/graphics.phoi"),
+    ),
+    (
+        "love.keyboard",
+        include_str!("interfaces/love/keyboard.phoi"),
+    ),
+    ("love.mouse", include_str!("interfaces/love/mouse.phoi")),
+    ("love.timer", include_str!("interfaces/love/timer.phoi")),
+    ("love.window", include_str!("interfaces/love/window.phoi")),
+];
+
+/// The interface every program imports, with functions like `print`.
+const PRELUDE: &str = "std.base";
+
+/// The source of the bundled interface with the given import path.
+pub fn find(path: &str) -> Option<&'static str> {
+    INTERFACES
+        .iter()
+        .find(|(name, _)| *name == path)
+        .map(|(_, source)| *source)
+}
+
+/// The import paths of all bundled interfaces.
+pub fn paths() -> impl Iterator<Item = &'static str> {
+    INTERFACES.iter().map(|(name, _)| *name)
+}
+
+/// Adds the declarations of the prelude, of every interface the program
+/// imports and of the `extra` interfaces to the front of the program. Each
+/// interface is only added once. Imports that name no bundled interface are
+/// reported.
+pub fn resolve_imports(program: &mut Program, extra: &[String]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut imported: Vec<&str> = vec![PRELUDE];
+    imported.extend(extra.iter().map(String::as_str));
+    for decl in &program.top_level_decls {
+        let TopLevelDecl::ImportDecl(import) = decl else {
+            continue;
+        };
+        if find(&import.path).is_some() {
+            imported.push(&import.path);
+        } else {
+            diagnostics.push(
+                Diagnostic::error(
+                    codes::UNKNOWN_MODULE,
+                    format!("cannot find module `{}`", import.path),
+                    import.span,
+                )
+                .with_label("no such module".to_string())
+                .with_note(format!(
+                    "the bundled modules are {}",
+                    paths()
+                        .map(|path| format!("`{}`", path))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )),
+            );
+        }
+    }
+
+    let mut decls = Vec::new();
+    let mut seen: Vec<&str> = Vec::new();
+    for path in imported {
+        if seen.contains(&path) {
+            continue;
+        }
+        seen.push(path);
+        let source = find(path).unwrap_or_else(|| panic!("unknown interface `{}`", path));
+        let interface = parser::parse(source)
+            .unwrap_or_else(|_| panic!("the bundled interface `{}` does not parse", path));
+        decls.extend(interface.top_level_decls);
+    }
+    program.top_level_decls.splice(0..0, decls);
+    diagnostics
+}
@@ -0,0 +1,26 @@
+use std::borrow::Cow;
+
+/// Lua keywords that are ordinary identifiers in Phobos.
+pub const LUA_KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/// Whether `path` is a dotted path of Lua names, like `love.graphics.draw`.
+pub fn is_lua_path(path: &str) -> bool {
+    path.split('.').all(|name| {
+        name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !LUA_KEYWORDS.contains(&name)
+    })
+}
+
+/// The Lua name for a Phobos identifier. Identifiers that are Lua keywords
+/// get a trailing underscore so that the output still loads.
+pub fn lua_name(name: &str) -> Cow<'_, str> {
+    if LUA_KEYWORDS.contains(&name) {
+        Cow::Owned(format!("{}_", name))
+    } else {
+        Cow::Borrowed(name)
+    }
+}
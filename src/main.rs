@@ -1,84 +1,137 @@
 use lalrpop_util::lalrpop_mod;
 
-lalrpop_mod!(pub phobos_grammar);
+lalrpop_mod!(
+    #[allow(clippy::ptr_arg)]
+    pub phobos_grammar
+);
 
 pub mod ast;
 pub mod codegen;
+pub mod diagnostic;
+pub mod interfaces;
+pub mod lua;
+pub mod parser;
 pub mod types;
 
 use std::env;
 use std::fs::File;
 use std::io::{self, BufRead, BufReader};
 
+const USAGE: &str =
+    "usage: phobos [--host TABLE] [--import MODULE]... [--redeclaration warn|deny] [FILE]";
+
 fn main() -> io::Result<()> {
-    let mut reader = open_reader()?;
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("error: {}\n{}", message, USAGE);
+            std::process::exit(1);
+        }
+    };
+    let (file_name, mut reader) = open_reader(args.input)?;
     let mut input = String::new();
     reader.read_to_string(&mut input)?;
-    let program = match phobos_grammar::ProgramParser::new().parse(&mut input) {
+    let mut program = match parser::parse(&input) {
         Ok(program) => program,
-
-        Err(e) => match e {
-            lalrpop_util::ParseError::InvalidToken { location }
-            | lalrpop_util::ParseError::UnrecognizedToken {
-                token: (location, _, _),
-                ..
-            }
-            | lalrpop_util::ParseError::UnrecognizedEof { location, .. } => {
-                let (line, col) = byte_offset_to_line_col(&input, location);
-                eprintln!("Parse error at line {}, column {}", line, col);
-                panic!();
-            }
-            other => {
-                eprintln!("Other parse error: {:?}", other);
-                panic!();
+        Err(diagnostics) => {
+            for diagnostic in &diagnostics {
+                eprintln!("{}", diagnostic.render(&file_name, &input));
             }
-        },
+            std::process::exit(1);
+        }
     };
-    types::typecheck(&program).expect("Type checking failed");
+    let diagnostics = interfaces::resolve_imports(&mut program, &args.imports);
+    if !diagnostics.is_empty() {
+        for diagnostic in &diagnostics {
+            eprintln!("{}", diagnostic.render(&file_name, &input));
+        }
+        std::process::exit(1);
+    }
+    let (diagnostics, info) = types::typecheck_with_options(&program, &args.type_options);
+    for diagnostic in &diagnostics {
+        eprintln!("{}", diagnostic.render(&file_name, &input));
+    }
+    if diagnostics.iter().any(|d| d.is_error()) {
+        std::process::exit(1);
+    }
     let stdout = std::io::stdout();
     let mut handle = stdout.lock();
-    codegen::generate_code(&mut handle, &program).expect("Failed to generate code");
+    codegen::generate_code(&mut handle, &program, &info, &args.codegen_options)
+        .expect("Failed to generate code");
     Ok(())
 }
 
-fn open_reader() -> io::Result<Box<dyn BufRead>> {
-    // Get command-line arguments, skipping the first one (program name)
-    let args: Vec<String> = env::args().skip(1).collect();
-
-    // Choose the input source: file or stdin
-    if let Some(filename) = args.first() {
-        let file = File::open(filename)?;
-        Ok(Box::new(BufReader::new(file)))
-    } else {
-        Ok(Box::new(BufReader::new(io::stdin())))
-    }
+/// The command line: an optional input file, interfaces to import in
+/// addition to those the program imports, and settings for the output.
+struct Args {
+    input: Option<String>,
+    imports: Vec<String>,
+    type_options: types::Options,
+    codegen_options: codegen::Options,
 }
 
-pub fn byte_offset_to_line_col(source: &str, offset: usize) -> (usize, usize) {
-    let mut line = 1;
-    let mut col = 1;
-    let mut i = 0;
-
-    for ch in source.chars() {
-        if i == offset {
-            break;
-        }
-        if ch == '\n' {
-            line += 1;
-            col = 1;
-        } else {
-            col += 1;
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args {
+        input: None,
+        imports: Vec::new(),
+        type_options: types::Options::default(),
+        codegen_options: codegen::Options::default(),
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--host" => {
+                let host = args
+                    .next()
+                    .ok_or_else(|| "`--host` needs the name of a table".to_string())?;
+                // The host table is written into the generated code as is
+                if !lua::is_lua_path(&host) {
+                    return Err(format!("`{}` is not a valid Lua name", host));
+                }
+                parsed.codegen_options.host = host;
+            }
+            "--import" => {
+                let module = args
+                    .next()
+                    .ok_or_else(|| "`--import` needs the name of a module".to_string())?;
+                if interfaces::find(&module).is_none() {
+                    return Err(format!("cannot find module `{}`", module));
+                }
+                parsed.imports.push(module);
+            }
+            "--redeclaration" => {
+                parsed.type_options.redeclaration = match args.next().as_deref() {
+                    Some("warn") => types::Redeclaration::Warn,
+                    Some("deny") => types::Redeclaration::Deny,
+                    _ => return Err("`--redeclaration` needs `warn` or `deny`".to_string()),
+                };
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option `{}`", arg)),
+            _ if parsed.input.is_some() => return Err(format!("unexpected argument `{}`", arg)),
+            _ => parsed.input = Some(arg),
         }
-        i += ch.len_utf8();
     }
+    Ok(parsed)
+}
 
-    (line, col)
+fn open_reader(input: Option<String>) -> io::Result<(String, Box<dyn BufRead>)> {
+    // Choose the input source: file or stdin
+    if let Some(filename) = input {
+        let file = File::open(&filename)?;
+        Ok((filename, Box::new(BufReader::new(file))))
+    } else {
+        Ok(("<stdin>".to_string(), Box::new(BufReader::new(io::stdin()))))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::ast::Program;
+    use super::ast::{ExprKind, Program, Span, StmtKind, TopLevelDecl};
+    use super::codegen;
+    use super::diagnostic;
+    use super::interfaces;
+    use super::parser;
     use super::phobos_grammar;
+    use super::types;
 
     #[allow(dead_code)]
     fn program_to_string(program: &Program) -> String {
@@ -94,18 +147,44 @@ mod tests {
     fn test_parse_extern_decl() {
         let code = "extern foo(n: Int): Int";
         let program = phobos_grammar::ProgramParser::new()
-            .parse(code)
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let stringified = program_to_string(&program);
+
+        assert_eq!(stringified, code);
+    }
+
+    #[test]
+    fn test_parse_extern_decl_with_lua_name() {
+        let code = "extern sin(x: Number): Number = \"math.sin\"";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
             .expect("Failed to parse program");
         let stringified = program_to_string(&program);
 
         assert_eq!(stringified, code);
+        assert!(types::typecheck(&program).is_empty());
+    }
+
+    #[test]
+    fn test_bundled_interfaces_typecheck() {
+        for path in interfaces::paths() {
+            let source = interfaces::find(path).unwrap();
+            let program = parser::parse(source)
+                .unwrap_or_else(|_| panic!("interface `{}` does not parse", path));
+            assert!(
+                types::typecheck(&program).is_empty(),
+                "interface `{}` does not typecheck",
+                path
+            );
+        }
     }
 
     #[test]
     fn test_parse_function_decl() {
         let code = "fn foo(n: Int): Int { return (n + 1); }";
         let program = phobos_grammar::ProgramParser::new()
-            .parse(code)
+            .parse(&mut Vec::new(), code)
             .expect("Failed to parse program");
         let stringified = program_to_string(&program);
 
@@ -113,13 +192,470 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_type_decl() {
-        let code = "type Foo { name: String, age: Int }";
+    fn test_parse_record_decl() {
+        let code = "record Foo { name: String, age: Int }";
         let program = phobos_grammar::ProgramParser::new()
-            .parse(code)
+            .parse(&mut Vec::new(), code)
             .expect("Failed to parse program");
         let stringified = program_to_string(&program);
 
         assert_eq!(stringified, code);
     }
+
+    #[test]
+    fn test_record_literal_and_field_access() {
+        let code = "record P { x: Number, y: Number }\n\
+                    fn f(): Number { let p: P = { x: 1, y: 2 }; p.x = p.y; return p.x; }";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let stringified = program_to_string(&program);
+
+        assert_eq!(
+            stringified,
+            "record P { x: Number, y: Number }\n\
+             fn f(): Number { let p: P = { x: 1, y: 2 };\n\
+             p.x = p.y;\n\
+             return p.x; }"
+        );
+        assert!(types::typecheck(&program).is_empty());
+    }
+
+    #[test]
+    fn test_parse_array_types_and_indexing() {
+        let code = "fn f(xs: [[Number]]): Number { return #xs[1] + [1, 2][xs[1][2]]; }";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let stringified = program_to_string(&program);
+
+        assert_eq!(
+            stringified,
+            "fn f(xs: [[Number]]): Number { return ((#xs[1]) + [1, 2][xs[1][2]]); }"
+        );
+        assert!(types::typecheck(&program).is_empty());
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = |args: &[&str]| super::parse_args(args.iter().map(|arg| arg.to_string()));
+
+        let parsed = args(&["--host", "game", "pong.pho"]).expect("valid arguments");
+        assert_eq!(parsed.input.as_deref(), Some("pong.pho"));
+        assert_eq!(parsed.codegen_options.host, "game");
+        let defaults = args(&[]).expect("valid arguments");
+        assert_eq!(defaults.codegen_options.host, "love");
+        assert_eq!(
+            defaults.type_options.redeclaration,
+            types::Redeclaration::Warn
+        );
+        assert!(args(&["--host"]).is_err());
+        assert!(args(&["--host", "love.2d"]).is_err());
+        assert!(args(&["--hots", "game"]).is_err());
+        assert!(args(&["a.pho", "b.pho"]).is_err());
+
+        let parsed =
+            args(&["--import", "std.math", "--import", "love.graphics"]).expect("valid arguments");
+        assert_eq!(parsed.imports, ["std.math", "love.graphics"]);
+        assert!(args(&["--import", "std.maths"]).is_err());
+
+        let parsed = args(&["--redeclaration", "deny"]).expect("valid arguments");
+        assert_eq!(
+            parsed.type_options.redeclaration,
+            types::Redeclaration::Deny
+        );
+        assert!(args(&["--redeclaration", "error"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_loops() {
+        let code = "fn f(xs: [Number]): Number { \
+                    for i in 0..#xs + 1 { break; } \
+                    for i in 1..=2 { continue; } \
+                    for x in xs { } \
+                    while 1 .. 2 == \"12\" { } \
+                    return 0; }";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let stringified = program_to_string(&program);
+
+        assert_eq!(
+            stringified,
+            "fn f(xs: [Number]): Number { for i in 0..((#xs) + 1) { break; }\n\
+             for i in 1..=2 { continue; }\n\
+             for x in xs {  }\n\
+             while ((1 .. 2) == \"12\") {  }\n\
+             return 0; }"
+        );
+        assert!(types::typecheck(&program).is_empty());
+    }
+
+    #[test]
+    fn test_parse_procedures() {
+        let code = "fn f(n: Number) { while n > 0 { return; } } \
+                    extern g(): Void";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let stringified = program_to_string(&program);
+
+        assert_eq!(
+            stringified,
+            "fn f(n: Number): Void { while (n > 0) { return; } }\n\
+             extern g(): Void"
+        );
+        assert!(types::typecheck(&program).is_empty());
+    }
+
+    #[test]
+    fn test_parse_optionals() {
+        let code = "fn f(xs: [Number?]?): Number? { \
+                    if xs != nil { return xs[1] ?? xs[2] ?? 1 + 2; } \
+                    return nil; }";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let stringified = program_to_string(&program);
+
+        assert_eq!(
+            stringified,
+            "fn f(xs: [Number?]?): Number? { if (xs != nil) { return (xs[1] ?? (xs[2] ?? (1 + 2))); } None\n\
+             return nil; }"
+        );
+        // it parses, but an array can't hold optional values
+        let errors: Vec<(u16, &str)> = types::typecheck(&program)
+            .iter()
+            .map(|d| (d.code, &code[d.span.start..d.span.end]))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![(diagnostic::codes::OPTIONAL_ELEMENT, "[Number?]")]
+        );
+    }
+
+    #[test]
+    fn test_parse_enums_and_match() {
+        let code = "enum Shape { Circle(r: Number), Rect(w: Number, h: Number), Empty }\n\
+                    fn f(s: Shape): Number { match s { Circle(r) => { return r; } \
+                    Rect(w, h) => { return w * h; } _ => { return 0; } } }";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let stringified = program_to_string(&program);
+
+        assert_eq!(
+            stringified,
+            "enum Shape { Circle(r: Number), Rect(w: Number, h: Number), Empty }\n\
+             fn f(s: Shape): Number { match s { Circle(r) => { return r; } \
+             Rect(w, h) => { return (w * h); } _ => { return 0; } } }"
+        );
+        assert!(types::typecheck(&program).is_empty());
+    }
+
+    #[test]
+    fn test_parse_function_types_and_lambdas() {
+        let code = "fn f(g: (Number, [Number]) -> Number?, h: (() -> Void)?): (Number) -> Number { \
+                    return fn(x: Number): Number { return g(x, [x])[1] ?? x; }; }";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let stringified = program_to_string(&program);
+
+        assert_eq!(
+            stringified,
+            "fn f(g: (Number, [Number]) -> Number?\nh: (() -> Void)?): (Number) -> Number { \
+             return fn(x: Number): Number { return (g([x, [x]])[1] ?? x); }; }"
+        );
+    }
+
+    #[test]
+    fn test_parse_attaches_spans() {
+        let code = "fn foo(n: Number): Number {\n    return n + 1;\n}";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let TopLevelDecl::FunctionDecl(func) = &program.top_level_decls[0] else {
+            panic!("expected a function declaration");
+        };
+        assert_eq!(func.span, Span::new(0, code.len()));
+        assert_eq!(
+            &code[func.params[0].span.start..func.params[0].span.end],
+            "n: Number"
+        );
+        let stmt = &func.body.stmts[0];
+        assert_eq!(&code[stmt.span.start..stmt.span.end], "return n + 1;");
+        let StmtKind::Return(Some(expr)) = &stmt.kind else {
+            panic!("expected a return statement");
+        };
+        assert!(matches!(expr.kind, ExprKind::BinaryExp(..)));
+        assert_eq!(&code[expr.span.start..expr.span.end], "n + 1");
+    }
+
+    #[test]
+    fn test_type_error_points_at_expression() {
+        let code = "fn foo(n: Number): Number {\n    return n + \"one\";\n}";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let error = &types::typecheck(&program)[0];
+        assert_eq!(&code[error.span.start..error.span.end], "n + \"one\"");
+        assert_eq!(
+            diagnostic::byte_offset_to_line_col(code, error.span.start),
+            (2, 12)
+        );
+    }
+
+    #[test]
+    fn test_render_diagnostic() {
+        let code = "fn foo(n: Number): Number {\n    let s: Number = \"one\";\n    return s;\n}";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let error = &types::typecheck(&program)[0];
+        assert_eq!(error.code, diagnostic::codes::MISMATCHED_TYPES);
+        assert_eq!(
+            error.render("foo.pho", code),
+            "error[E0003]: mismatched types\n \
+             --> foo.pho:2:21\n  \
+             |\n\
+             2 |     let s: Number = \"one\";\n  \
+             |            ------ expected due to this\n  \
+             |                     ^^^^^ expected `Number`, found `String`\n"
+        );
+    }
+
+    #[test]
+    fn test_typecheck_reports_every_error() {
+        let code = "fn f(n: Number): Number {\n\
+                        let a: Number = \"one\";\n\
+                        let b: Number = a + missing;\n\
+                        return b + \"two\";\n\
+                    }\n\
+                    fn g(): Number {\n\
+                        return f(\"three\");\n\
+                    }";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let errors: Vec<(u16, &str)> = types::typecheck(&program)
+            .iter()
+            .map(|d| (d.code, &code[d.span.start..d.span.end]))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (diagnostic::codes::MISMATCHED_TYPES, "\"one\""),
+                (diagnostic::codes::UNDEFINED_IDENTIFIER, "missing"),
+                (diagnostic::codes::INVALID_OPERANDS, "b + \"two\""),
+                (diagnostic::codes::MISMATCHED_TYPES, "\"three\""),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redeclaration_policy() {
+        let code = "fn f(): Number { let a: Number = 1; { let a: Number = 2; } let a: Number = 3; return a; }";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+
+        let warnings = types::typecheck(&program);
+        assert_eq!(warnings.len(), 1);
+        assert!(!warnings[0].is_error());
+        assert_eq!(
+            &code[warnings[0].span.start..warnings[0].span.end],
+            "let a: Number = 3;"
+        );
+
+        let options = types::Options {
+            redeclaration: types::Redeclaration::Deny,
+        };
+        let (errors, _) = types::typecheck_with_options(&program, &options);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].is_error());
+    }
+
+    #[test]
+    fn test_parse_reports_every_syntax_error() {
+        let code = "fn f(n: Number): Number {\n\
+                        let a: Number = n + * 2;\n\
+                        return a;\n\
+                    }\n\
+                    fn g(: Number {}\n\
+                    fn h(n: Number): Number {\n\
+                        return n\n\
+                    }";
+        let errors = parser::parse(code).err().expect("expected syntax errors");
+        let errors: Vec<(u16, &str)> = errors
+            .iter()
+            .map(|d| (d.code, &code[d.span.start..d.span.end]))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (diagnostic::codes::UNEXPECTED_TOKEN, "*"),
+                (diagnostic::codes::UNEXPECTED_TOKEN, ":"),
+                (diagnostic::codes::UNEXPECTED_TOKEN, "}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_boolean_operators() {
+        let code = "fn f(a: Bool): Bool { return !a || a && true == false; }";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let stringified = program_to_string(&program);
+
+        assert_eq!(
+            stringified,
+            "fn f(a: Bool): Bool { return ((!a) || (a && (true == false))); }"
+        );
+        assert!(types::typecheck(&program).is_empty());
+    }
+
+    #[test]
+    fn test_parse_operator_precedence() {
+        let code = "fn f(x: Number): String { return -x ^ 2 ^ 3 % 4 .. \"a\" .. x < 1; }";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let stringified = program_to_string(&program);
+
+        assert_eq!(
+            stringified,
+            "fn f(x: Number): String { return ((((-(x ^ (2 ^ 3))) % 4) .. (\"a\" .. x)) < 1); }"
+        );
+    }
+
+    #[test]
+    fn test_parse_generics() {
+        let code = "record Pair<A, B> { first: A, second: B }\n\
+                    fn swap<A, B>(p: Pair<A, B>): Pair<B, A> { return { first: p.second, second: p.first }; }";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let stringified = program_to_string(&program);
+
+        assert_eq!(
+            stringified,
+            "record Pair<A, B> { first: A, second: B }\n\
+             fn swap<A, B>(p: Pair<A, B>): Pair<B, A> { return { first: p.second, second: p.first }; }"
+        );
+        assert!(types::typecheck(&program).is_empty());
+    }
+
+    #[test]
+    fn test_parse_inferred_let() {
+        let code = "fn f(a: Number): Number { let b = a * 2; let c: Number = b; return c; }";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let stringified = program_to_string(&program);
+
+        assert_eq!(
+            stringified,
+            "fn f(a: Number): Number { let b = (a * 2);\nlet c: Number = b;\nreturn c; }"
+        );
+        assert!(types::typecheck(&program).is_empty());
+    }
+
+    #[test]
+    fn test_parse_interface_and_impl() {
+        let code = "interface Shape { fn area(self): Number fn scale(self, k: Number) }\n\
+                    record Square { side: Number }\n\
+                    impl Shape for Square {\n\
+                        fn area(self): Number { return self.side * self.side; }\n\
+                        fn scale(self, k: Number) { self.side = self.side * k; }\n\
+                    }";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let stringified = program_to_string(&program);
+
+        assert_eq!(
+            stringified,
+            "interface Shape { fn area(self): Number fn scale(self, k: Number): Void }\n\
+             record Square { side: Number }\n\
+             impl Shape for Square { \
+             fn area(self): Number { return (self.side * self.side); } \
+             fn scale(self\nk: Number): Void { self.side = (self.side * k); } }"
+        );
+        assert!(types::typecheck(&program).is_empty());
+    }
+
+    #[test]
+    fn test_generate_method_call() {
+        let code = "record Point { x: Number }\n\
+                    impl Point { fn twice(self): Number { return self.x * 2; } }\n\
+                    fn f(p: Point): Number { return p.twice(); }";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let (diagnostics, info) =
+            types::typecheck_with_options(&program, &types::Options::default());
+        assert!(diagnostics.is_empty());
+        let mut output = Vec::new();
+        codegen::generate_code(&mut output, &program, &info, &codegen::Options::default())
+            .expect("Failed to generate code");
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "local Point = {}\n\
+             Point.__index = Point\n\
+             function Point.twice(self)\n\
+             \x20   return (self.x*2)\n\
+             end\n\
+             function f(p)\n\
+             \x20   return p:twice()\n\
+             end\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_if_chain_and_assignment() {
+        let code = "fn f(x: Number): Number {\n\
+                        let s: Number = 0;\n\
+                        if x < 0 { s = -1; } else if x != 0 { s = 1; }\n\
+                        if s == 0 { return 0; s = 2; }\n\
+                        return s;\n\
+                    }";
+        let program = phobos_grammar::ProgramParser::new()
+            .parse(&mut Vec::new(), code)
+            .expect("Failed to parse program");
+        let warnings: Vec<(u16, &str)> = types::typecheck(&program)
+            .iter()
+            .map(|d| (d.code, &code[d.span.start..d.span.end]))
+            .collect();
+        assert_eq!(
+            warnings,
+            vec![(diagnostic::codes::UNREACHABLE_CODE, "s = 2;")]
+        );
+        let mut output = Vec::new();
+        codegen::generate_code(
+            &mut output,
+            &program,
+            &types::TypeInfo::default(),
+            &codegen::Options::default(),
+        )
+        .expect("Failed to generate code");
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "function f(x)\n\
+             \x20   local s = 0\n\
+             \x20   if (x<0) then\n\
+             \x20       s = (-1)\n\
+             \x20   elseif (x~=0) then\n\
+             \x20       s = 1\n\
+             \x20   end\n\
+             \x20   if (s==0) then\n\
+             \x20       return 0\n\
+             \x20   end\n\
+             \x20   return s\n\
+             end\n"
+        );
+    }
 }
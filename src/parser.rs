@@ -0,0 +1,101 @@
+use lalrpop_util::ParseError;
+use lalrpop_util::lexer::Token;
+
+use crate::ast::{Program, Span};
+use crate::diagnostic::{Diagnostic, codes};
+use crate::phobos_grammar;
+
+/// Parses a program, recovering from syntax errors at statement and
+/// declaration boundaries so that every error in the source is reported.
+pub fn parse(source: &str) -> Result<Program, Vec<Diagnostic>> {
+    let mut recovered = Vec::new();
+    let result = phobos_grammar::ProgramParser::new().parse(&mut recovered, source);
+    let mut diagnostics: Vec<Diagnostic> = recovered
+        .into_iter()
+        .map(|recovery| to_diagnostic(recovery.error, source))
+        .collect();
+    match result {
+        Ok(program) if diagnostics.is_empty() => Ok(program),
+        Ok(_) => Err(diagnostics),
+        Err(error) => {
+            diagnostics.push(to_diagnostic(error, source));
+            Err(diagnostics)
+        }
+    }
+}
+
+fn to_diagnostic(error: ParseError<usize, Token<'_>, &'static str>, source: &str) -> Diagnostic {
+    match error {
+        ParseError::InvalidToken { location } => {
+            let len = source[location..].chars().next().map_or(0, char::len_utf8);
+            Diagnostic::error(
+                codes::INVALID_TOKEN,
+                "unknown start of token".to_string(),
+                Span::new(location, location + len),
+            )
+        }
+        ParseError::UnrecognizedEof { location, expected } => Diagnostic::error(
+            codes::UNEXPECTED_EOF,
+            format!(
+                "expected {}, found end of file",
+                describe_expected(&expected)
+            ),
+            Span::new(location, location),
+        )
+        .with_label("unexpected end of file".to_string()),
+        ParseError::UnrecognizedToken {
+            token: (start, token, end),
+            expected,
+        } => Diagnostic::error(
+            codes::UNEXPECTED_TOKEN,
+            format!(
+                "expected {}, found `{}`",
+                describe_expected(&expected),
+                token
+            ),
+            Span::new(start, end),
+        )
+        .with_label(format!("unexpected `{}`", token)),
+        ParseError::ExtraToken {
+            token: (start, token, end),
+        } => Diagnostic::error(
+            codes::UNEXPECTED_TOKEN,
+            format!("unexpected `{}` after the end of the program", token),
+            Span::new(start, end),
+        ),
+        ParseError::User { error } => {
+            Diagnostic::error(codes::UNEXPECTED_TOKEN, error.to_string(), Span::new(0, 0))
+        }
+    }
+}
+
+/// Turns lalrpop's list of expected terminals into readable English.
+fn describe_expected(expected: &[String]) -> String {
+    let mut names: Vec<String> = Vec::new();
+    for name in expected.iter().map(|t| describe_terminal(t)) {
+        // several regexes may describe the same kind of token
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    match names.as_slice() {
+        [] => "something else".to_string(),
+        [name] => name.clone(),
+        [names @ .., last] => format!("one of {} or {}", names.join(", "), last),
+    }
+}
+
+fn describe_terminal(terminal: &str) -> String {
+    // Regex terminals are printed by lalrpop as `r#"..."#`
+    if terminal.starts_with('r') {
+        if terminal.contains("a-zA-Z") {
+            "identifier".to_string()
+        } else if terminal.contains("0-9") {
+            "number".to_string()
+        } else {
+            "string literal".to_string()
+        }
+    } else {
+        format!("`{}`", terminal.trim_matches('"'))
+    }
+}
@@ -1,5 +1,13 @@
-use crate::ast::{self, Expr, Opcode, Program};
-use crate::ast::{Block, FunctionDecl, Stmt, TopLevelDecl};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+
+use crate::ast::{self, Expr, ExprKind, FieldInit, Opcode, Program, Span, UnaryOp};
+use crate::ast::{
+    Block, EnumDecl, ExternDecl, FunctionDecl, GameDecl, ImplDecl, InterfaceDecl, MatchArm,
+    ParamDecl, PatternKind, RecordDecl, Stmt, StmtKind, TopLevelDecl, TypeKind,
+};
+use crate::diagnostic::{Diagnostic, codes};
+use crate::lua::is_lua_path;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Type {
@@ -8,35 +16,79 @@ pub enum Type {
     String,
     Bool,
     Function(Vec<Type>, Box<Type>),
-    Record(String, Vec<Field>),
+    /// A record, by name, with the type arguments of a generic record. Its
+    /// fields are looked up in the `TypeEnvironment`, which lets records
+    /// refer to themselves.
+    Record(String, Vec<Type>),
+    /// An enum, by name. Its variants are looked up in the `TypeEnvironment`.
+    Enum(String),
+    /// An interface, by name: any record that implements it
+    Interface(String),
+    Array(Box<Type>),
+    /// A value of the inner type or `nil`
+    Optional(Box<Type>),
+    /// The type of the `nil` literal, which fits any optional type
+    Nil,
+    /// A type parameter of the generic function or record being checked.
+    /// It stands for any type, so it only matches itself.
+    Param(String),
+    /// A generic function, with the names of its type parameters. It is
+    /// given a plain function type wherever it is used.
+    Generic(Vec<String>, Box<Type>),
+    /// The type of an expression that failed to typecheck. It is compatible
+    /// with every other type, so one mistake is only reported once.
+    Error,
 }
 
-impl From<ast::Type> for Type {
-    fn from(ty: ast::Type) -> Self {
-        match ty.name.as_str() {
-            "Number" => Type::Number,
-            "String" => Type::String,
-            _ => unimplemented!(),
+impl Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Void => write!(f, "Void"),
+            Type::Number => write!(f, "Number"),
+            Type::String => write!(f, "String"),
+            Type::Bool => write!(f, "Bool"),
+            Type::Function(params, ret) => {
+                write!(f, "(")?;
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", param)?;
+                }
+                write!(f, ") -> {}", ret)
+            }
+            Type::Record(name, args) if !args.is_empty() => {
+                write!(f, "{}<", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ">")
+            }
+            Type::Record(name, _)
+            | Type::Enum(name)
+            | Type::Interface(name)
+            | Type::Param(name) => {
+                write!(f, "{}", name)
+            }
+            Type::Generic(params, ty) => write!(f, "<{}>{}", params.join(", "), ty),
+            Type::Array(element) => write!(f, "[{}]", element),
+            Type::Optional(inner) if matches!(**inner, Type::Function(..)) => {
+                write!(f, "({})?", inner)
+            }
+            Type::Optional(inner) => write!(f, "{}?", inner),
+            Type::Nil => write!(f, "nil"),
+            Type::Error => write!(f, "{{error}}"),
         }
     }
 }
 
-impl From<FunctionDecl> for Type {
-    fn from(func: FunctionDecl) -> Self {
-        Type::Function(
-            func.params
-                .iter()
-                .map(|param| param.ty.clone().into())
-                .collect(),
-            Box::new(func.ret.into()),
-        )
-    }
-}
-
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Field {
-    name: String,
-    ty: Type,
+    pub name: String,
+    pub ty: Type,
 }
 
 impl Field {
@@ -45,14 +97,213 @@ impl Field {
     }
 }
 
-#[derive(Debug)]
+/// A variant of an enum, with the fields it carries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+    pub name: String,
+    pub fields: Vec<Field>,
+}
+
+impl Variant {
+    pub fn new(name: String, fields: Vec<Field>) -> Self {
+        Variant { name, fields }
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct TypeEnvironment {
     types: Vec<(String, Type)>,
+    /// Where each open scope starts in `types`. Definitions before the first
+    /// scope are global.
+    scopes: Vec<usize>,
+    records: HashMap<String, Vec<Field>>,
+    /// The type parameters of generic records
+    record_params: HashMap<String, Vec<String>>,
+    enums: HashMap<String, Vec<Variant>>,
+    /// The methods of each interface, without `self`
+    interfaces: HashMap<String, Vec<Field>>,
+    /// The methods of each record that has an `impl`, without `self`
+    methods: HashMap<String, Vec<Field>>,
+    /// The interfaces each record implements
+    implementations: HashMap<String, Vec<String>>,
 }
 
 impl TypeEnvironment {
     pub fn new() -> Self {
-        TypeEnvironment { types: Vec::new() }
+        TypeEnvironment {
+            types: Vec::new(),
+            scopes: Vec::new(),
+            records: HashMap::new(),
+            record_params: HashMap::new(),
+            enums: HashMap::new(),
+            interfaces: HashMap::new(),
+            methods: HashMap::new(),
+            implementations: HashMap::new(),
+        }
+    }
+
+    pub fn push_scope(&mut self) {
+        self.scopes.push(self.types.len());
+    }
+
+    /// Closes the innermost scope, forgetting everything defined in it.
+    pub fn pop_scope(&mut self) {
+        let start = self.scopes.pop().expect("no scope to pop");
+        self.types.truncate(start);
+    }
+
+    pub fn define_record(&mut self, name: &str, fields: Vec<Field>) {
+        self.records.insert(name.to_string(), fields);
+    }
+
+    pub fn get_record(&self, name: &str) -> Option<&[Field]> {
+        self.records.get(name).map(Vec::as_slice)
+    }
+
+    pub fn define_record_params(&mut self, name: &str, params: Vec<String>) {
+        self.record_params.insert(name.to_string(), params);
+    }
+
+    pub fn get_record_params(&self, name: &str) -> &[String] {
+        self.record_params.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// The fields of a record, with the type arguments of a generic record
+    /// put in for its type parameters.
+    pub fn record_fields(&self, name: &str, args: &[Type]) -> Vec<Field> {
+        let substitution: HashMap<String, Type> = self
+            .get_record_params(name)
+            .iter()
+            .cloned()
+            .zip(args.iter().cloned())
+            .collect();
+        self.get_record(name)
+            .unwrap_or_default()
+            .iter()
+            .map(|f| Field::new(f.name.clone(), substitute(&f.ty, &substitution)))
+            .collect()
+    }
+
+    /// The element type of an array in `ty` that may be `nil`, if there is
+    /// one. The fields of generic records are looked at with their type
+    /// arguments put in, as those are where such arrays can hide.
+    pub fn optional_element(&self, ty: &Type) -> Option<Type> {
+        self.find_optional_element(ty, &mut Vec::new())
+    }
+
+    fn find_optional_element(&self, ty: &Type, seen: &mut Vec<String>) -> Option<Type> {
+        match ty {
+            Type::Array(element) if matches!(**element, Type::Optional(_) | Type::Nil) => {
+                Some((**element).clone())
+            }
+            Type::Array(inner) | Type::Optional(inner) => self.find_optional_element(inner, seen),
+            Type::Function(params, ret) => params
+                .iter()
+                .chain(std::iter::once(&**ret))
+                .find_map(|ty| self.find_optional_element(ty, seen)),
+            // a recursive record is only looked at once
+            Type::Record(name, args) if !args.is_empty() && !seen.contains(&ty.to_string()) => {
+                seen.push(ty.to_string());
+                self.record_fields(name, args)
+                    .iter()
+                    .find_map(|f| self.find_optional_element(&f.ty, seen))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn define_enum(&mut self, name: &str, variants: Vec<Variant>) {
+        self.enums.insert(name.to_string(), variants);
+    }
+
+    pub fn get_enum(&self, name: &str) -> Option<&[Variant]> {
+        self.enums.get(name).map(Vec::as_slice)
+    }
+
+    /// The enum that has a variant called `name`, if any.
+    pub fn enum_of_variant(&self, name: &str) -> Option<&str> {
+        self.enums
+            .iter()
+            .find(|(_, variants)| variants.iter().any(|v| v.name == name))
+            .map(|(enum_name, _)| enum_name.as_str())
+    }
+
+    pub fn define_interface(&mut self, name: &str, methods: Vec<Field>) {
+        self.interfaces.insert(name.to_string(), methods);
+    }
+
+    pub fn get_interface(&self, name: &str) -> Option<&[Field]> {
+        self.interfaces.get(name).map(Vec::as_slice)
+    }
+
+    /// Adds a method to a record. A record that has been given no methods
+    /// still has methods once an `impl` for it has been seen.
+    pub fn add_methods(&mut self, record: &str, methods: Vec<Field>) {
+        self.methods
+            .entry(record.to_string())
+            .or_default()
+            .extend(methods);
+    }
+
+    /// The methods of a record, or `None` if it has no `impl`.
+    pub fn get_methods(&self, record: &str) -> Option<&[Field]> {
+        self.methods.get(record).map(Vec::as_slice)
+    }
+
+    /// The methods of a record, with the type arguments of a generic record
+    /// put in for its type parameters, like `record_fields`.
+    pub fn record_methods(&self, name: &str, args: &[Type]) -> Vec<Field> {
+        let substitution: HashMap<String, Type> = self
+            .get_record_params(name)
+            .iter()
+            .cloned()
+            .zip(args.iter().cloned())
+            .collect();
+        self.get_methods(name)
+            .unwrap_or_default()
+            .iter()
+            .map(|m| Field::new(m.name.clone(), substitute(&m.ty, &substitution)))
+            .collect()
+    }
+
+    pub fn add_implementation(&mut self, record: &str, interface: &str) {
+        self.implementations
+            .entry(record.to_string())
+            .or_default()
+            .push(interface.to_string());
+    }
+
+    pub fn implements(&self, record: &str, interface: &str) -> bool {
+        self.implementations
+            .get(record)
+            .is_some_and(|interfaces| interfaces.iter().any(|i| i == interface))
+    }
+
+    /// Whether `name` is a record, an enum or an interface.
+    pub fn is_type_defined(&self, name: &str) -> bool {
+        self.records.contains_key(name)
+            || self.enums.contains_key(name)
+            || self.interfaces.contains_key(name)
+    }
+
+    /// Whether a value of type `ty_right` may be stored where a `ty_left` is
+    /// expected. `Type::Error` is compatible with everything so that an error
+    /// is not reported again wherever the broken value flows.
+    pub fn is_assignable(&self, ty_left: &Type, ty_right: &Type) -> bool {
+        match (ty_left, ty_right) {
+            (Type::Error, _) | (_, Type::Error) => true,
+            // `nil` and plain values fit where an optional is expected
+            (Type::Optional(_), Type::Nil) => true,
+            (Type::Optional(inner), Type::Optional(right_inner)) => {
+                self.is_assignable(inner, right_inner)
+            }
+            (Type::Optional(inner), _) => self.is_assignable(inner, ty_right),
+            // a record can be used as any interface it implements
+            (Type::Interface(interface), Type::Record(record, _)) => {
+                self.implements(record, interface)
+            }
+            _ => is_same_type(ty_left, ty_right),
+        }
     }
 
     pub fn set_type(&mut self, name: &str, ty: Type) {
@@ -66,206 +317,2392 @@ impl TypeEnvironment {
             .find(|(n, _)| n == name)
             .map(|(_, ty)| ty.clone())
     }
+
+    /// Whether `name` is defined in the innermost scope, rather than in one
+    /// around it.
+    pub fn is_defined_in_scope(&self, name: &str) -> bool {
+        let start = self.scopes.last().copied().unwrap_or(0);
+        self.types[start..].iter().any(|(n, _)| n == name)
+    }
+}
+
+/// What to do when a variable is declared twice in the same scope. The
+/// second declaration shadows the first either way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Redeclaration {
+    #[default]
+    Warn,
+    Deny,
+}
+
+/// Settings for the typechecker.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    pub redeclaration: Redeclaration,
+}
+
+/// What the code generator needs to know about a program that the syntax
+/// doesn't tell it.
+#[derive(Debug, Default)]
+pub struct TypeInfo {
+    /// The record built by each record literal of a record with methods, by
+    /// the span of the literal
+    pub record_literals: HashMap<Span, String>,
+    /// The spans of the calls that call a method, such as `d.draw()`
+    pub method_calls: HashSet<Span>,
+    /// The spans of the `??` expressions whose left side may be `false`
+    pub false_defaults: HashSet<Span>,
+}
+
+/// Typechecks a whole program with the default options.
+pub fn typecheck(program: &Program) -> Vec<Diagnostic> {
+    typecheck_with_options(program, &Options::default()).0
 }
 
-pub fn typecheck(program: &Program) -> Result<(), String> {
-    let mut env = TypeEnvironment::new();
-    for decl in &program.top_level_decls {
+/// Typechecks a whole program, returning every problem found and what the
+/// code generator needs to know. Checking continues past errors, so the
+/// result holds all independent mistakes.
+pub fn typecheck_with_options(program: &Program, options: &Options) -> (Vec<Diagnostic>, TypeInfo) {
+    let mut checker = TypeChecker::new(options.clone());
+    let decls = &program.top_level_decls;
+
+    // Collect every declaration before checking any function body, so that
+    // the order of declarations doesn't matter. Record, enum and interface
+    // names come first, as any signature may mention them, and then the
+    // constructors of enum variants.
+    let mut records = Vec::new();
+    let mut enums = Vec::new();
+    let mut interfaces = Vec::new();
+    for decl in decls {
         match decl {
-            TopLevelDecl::FunctionDecl(func) => typecheck_function_decl(func, &mut env)?,
-            TopLevelDecl::RecordDecl(name, fields) => {
-                let mut record_fields = Vec::new();
-                for field in fields {
-                    record_fields.push(Field::new(field.name.clone(), field.ty.clone().into()));
+            TopLevelDecl::RecordDecl(record) if checker.declare_record(record) => {
+                records.push(record)
+            }
+            TopLevelDecl::EnumDecl(enum_decl) if checker.declare_enum(enum_decl) => {
+                enums.push(enum_decl)
+            }
+            TopLevelDecl::InterfaceDecl(interface) if checker.declare_interface(interface) => {
+                interfaces.push(interface)
+            }
+            _ => {}
+        }
+    }
+    checker.pending_records = Some(Vec::new());
+    for record in records {
+        checker.define_record_fields(record);
+    }
+    for (record_ty, span) in checker.pending_records.take().unwrap_or_default() {
+        checker.check_record_elements(&record_ty, span);
+    }
+    for interface in interfaces {
+        checker.define_interface_methods(interface);
+    }
+    for enum_decl in &enums {
+        checker.define_variants(enum_decl);
+    }
+    for enum_decl in enums {
+        checker.declare_variants(enum_decl);
+    }
+    let mut functions = Vec::new();
+    let mut externs = Vec::new();
+    let mut impls = Vec::new();
+    for decl in decls {
+        match decl {
+            TopLevelDecl::FunctionDecl(func) => {
+                let signature = checker.declare_function(func, &functions, &externs);
+                functions.push((func, signature));
+            }
+            TopLevelDecl::ImplDecl(impl_decl) => {
+                if let Some(signatures) = checker.declare_methods(impl_decl) {
+                    impls.push((impl_decl, signatures));
                 }
-                let record_type = Type::Record(name.clone(), record_fields);
-                env.set_type(name, record_type);
             }
-            _ => unimplemented!(),
+            TopLevelDecl::ExternDecl(ext) => {
+                checker.typecheck_extern_decl(ext, &functions, &externs);
+                externs.push(ext);
+            }
+            // Records and enums have been declared above, and game callbacks
+            // are only visible to the host
+            TopLevelDecl::RecordDecl(_)
+            | TopLevelDecl::EnumDecl(_)
+            | TopLevelDecl::InterfaceDecl(_)
+            | TopLevelDecl::GameDecl(_) => {}
+            // Imports have been replaced by the externs they declare
+            TopLevelDecl::ImportDecl(_) => {}
         }
     }
-    Ok(())
+    checker.check_table_names(decls);
+
+    let mut functions = functions.into_iter();
+    for decl in decls {
+        match decl {
+            TopLevelDecl::FunctionDecl(_) => {
+                let (func, (param_types, ret)) = functions.next().unwrap();
+                checker.typecheck_function_body(func, param_types, ret);
+            }
+            TopLevelDecl::GameDecl(game) => checker.typecheck_game_decl(game),
+            _ => {}
+        }
+    }
+    for (impl_decl, signatures) in impls {
+        checker.typecheck_methods(impl_decl, signatures);
+    }
+    (checker.diagnostics, checker.info)
 }
 
-fn typecheck_function_decl(func: &FunctionDecl, env: &mut TypeEnvironment) -> Result<(), String> {
-    // Add the function itself to the environment
-    env.types.push((
-        func.name.clone(),
-        Type::Function(
-            func.params
-                .iter()
-                .map(|param| param.ty.clone().into())
-                .collect(),
-            Box::new(func.ret.clone().into()),
-        ),
-    ));
-    // Mark the state of the environment before checking the function body
-    let before_check = env.types.clone();
-    // Add the parameters to the environment
-    for param in &func.params {
-        env.types
-            .push((param.name.clone(), param.ty.clone().into()));
-    }
-    // Typecheck the function body
-    typecheck_block(&func.body, env, Some(func.ret.clone().into()))?;
-    // Restore the environment state before checking the function body
-    env.types = before_check;
-    Ok(())
-}
-
-fn typecheck_block(
-    block: &Block,
-    env: &mut TypeEnvironment,
-    return_type: Option<Type>,
-) -> Result<(), String> {
-    for stmt in &block.stmts {
-        typecheck_stmt(stmt, env, return_type.clone())?;
-    }
-    Ok(())
-}
-
-fn typecheck_stmt(
-    stmt: &Stmt,
-    env: &mut TypeEnvironment,
-    return_type: Option<Type>,
-) -> Result<(), String> {
-    match stmt {
-        Stmt::Assign(id, expr) => {
-            // look up the type of the identifier
-            let ty_left = env
-                .get_type(id)
-                .ok_or_else(|| format!("Undefined identifier: {}", id))?;
-            // derive the type of the expression
-            let ty_right = derive_type(expr, env)?;
-            // assignment is valid if the types are compatible
-            if is_assignable(&ty_left, &ty_right) {
-                Ok(())
-            } else {
-                Err(format!(
-                    "Type mismatch: {:?} cannot be assigned to {:?}",
-                    ty_right, ty_left
-                ))
-            }
-        }
-        Stmt::Let(id, ty, expr) => {
-            // type of the assigned value must match the declared type
-            let ty: Type = ty.clone().into();
-            let ty_expr = derive_type(expr, env)?;
-            if is_assignable(&ty, &ty_expr) {
-                // modify the environment with the type of the identifier
-                env.set_type(id, ty);
-                Ok(())
+struct TypeChecker {
+    env: TypeEnvironment,
+    options: Options,
+    diagnostics: Vec<Diagnostic>,
+    /// How many loops the statement being checked is in
+    loop_depth: usize,
+    /// The type parameters of the generic function or record being checked
+    type_params: Vec<String>,
+    /// What `Self` stands for in the interface or `impl` being checked
+    self_type: Option<Type>,
+    /// Generic record types met while the fields of records are defined,
+    /// whose arrays can only be checked once every record has its fields
+    pending_records: Option<Vec<(Type, Span)>>,
+    info: TypeInfo,
+}
+
+impl TypeChecker {
+    fn new(options: Options) -> Self {
+        TypeChecker {
+            env: TypeEnvironment::new(),
+            options,
+            diagnostics: Vec::new(),
+            loop_depth: 0,
+            type_params: Vec::new(),
+            self_type: None,
+            pending_records: None,
+            info: TypeInfo::default(),
+        }
+    }
+
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn resolve_type(&mut self, ty: &ast::Type) -> Type {
+        let name = match &ty.kind {
+            TypeKind::Named(name) => name.as_str(),
+            TypeKind::Generic(name, args) => {
+                let args: Vec<Type> = args.iter().map(|arg| self.resolve_type(arg)).collect();
+                return self.resolve_generic(ty, name, args);
+            }
+            TypeKind::Array(element) => {
+                let element_ty = self.resolve_type(element);
+                if let Type::Optional(_) = element_ty {
+                    self.report(optional_element_error(&element_ty, ty.span));
+                }
+                return Type::Array(Box::new(element_ty));
+            }
+            TypeKind::Optional(inner) => return optional(self.resolve_type(inner)),
+            TypeKind::Function(params, ret) => {
+                let params = params
+                    .iter()
+                    .map(|param| self.resolve_type(param))
+                    .collect();
+                return Type::Function(params, Box::new(self.resolve_type(ret)));
+            }
+        };
+        match name {
+            name if self.type_params.iter().any(|p| p == name) => Type::Param(name.to_string()),
+            "Self" if self.self_type.is_some() => self.self_type.clone().unwrap(),
+            "Void" => Type::Void,
+            "Number" => Type::Number,
+            "String" => Type::String,
+            "Bool" => Type::Bool,
+            name if self.env.get_record(name).is_some() => {
+                self.resolve_generic(ty, name, Vec::new())
+            }
+            name if self.env.get_enum(name).is_some() => Type::Enum(name.to_string()),
+            name if self.env.get_interface(name).is_some() => Type::Interface(name.to_string()),
+            name => {
+                self.report(
+                    Diagnostic::error(
+                        codes::UNDEFINED_TYPE,
+                        format!("cannot find type `{}` in this scope", name),
+                        ty.span,
+                    )
+                    .with_label("not found in this scope".to_string()),
+                );
+                Type::Error
+            }
+        }
+    }
+
+    /// Resolves a record given type arguments, which must be as many as the
+    /// record has type parameters.
+    fn resolve_generic(&mut self, ty: &ast::Type, name: &str, args: Vec<Type>) -> Type {
+        let params = if self.env.get_record(name).is_some() {
+            self.env.get_record_params(name).len()
+        } else if self.type_params.iter().any(|p| p == name)
+            || self.env.get_enum(name).is_some()
+            || self.env.get_interface(name).is_some()
+            || matches!(name, "Void" | "Number" | "String" | "Bool")
+        {
+            0
+        } else {
+            self.report(
+                Diagnostic::error(
+                    codes::UNDEFINED_TYPE,
+                    format!("cannot find type `{}` in this scope", name),
+                    ty.span,
+                )
+                .with_label("not found in this scope".to_string()),
+            );
+            return Type::Error;
+        };
+        if params != args.len() {
+            self.report(
+                Diagnostic::error(
+                    codes::TYPE_ARGUMENTS,
+                    format!(
+                        "type `{}` takes {} type argument(s) but {} were supplied",
+                        name,
+                        params,
+                        args.len()
+                    ),
+                    ty.span,
+                )
+                .with_label(format!("expected {} type argument(s)", params)),
+            );
+            return Type::Error;
+        }
+        let record_ty = Type::Record(name.to_string(), args);
+        // arrays of the type arguments themselves have been checked already
+        if let Type::Record(_, args) = &record_ty
+            && args.iter().any(|arg| matches!(arg, Type::Optional(_)))
+        {
+            match &mut self.pending_records {
+                Some(pending) => pending.push((record_ty.clone(), ty.span)),
+                None => self.check_record_elements(&record_ty, ty.span),
+            }
+        }
+        record_ty
+    }
+
+    /// Reports a generic record whose type arguments make one of its arrays
+    /// hold optional values.
+    fn check_record_elements(&mut self, record_ty: &Type, span: Span) {
+        if let Some(element) = self.env.optional_element(record_ty) {
+            self.report(
+                optional_element_error(&element, span)
+                    .with_note(format!("`{}` has an array of `{}`", record_ty, element)),
+            );
+        }
+    }
+
+    /// Checks that the type parameters of a generic declaration have
+    /// different names.
+    fn check_type_params(&mut self, type_params: &[String], span: Span) {
+        for (i, name) in type_params.iter().enumerate() {
+            if type_params[..i].contains(name) {
+                self.report(
+                    Diagnostic::error(
+                        codes::DUPLICATE_DEFINITION,
+                        format!("the type parameter `{}` is defined more than once", name),
+                        span,
+                    )
+                    .with_label(format!("`{}` redefined here", name)),
+                );
+            }
+        }
+    }
+
+    /// Checks that a record, enum or interface called `name` can be declared:
+    /// the name must not be taken by another type or be a builtin type,
+    /// which it could never be told apart from.
+    fn check_type_name(&mut self, kind: &str, name: &str, span: Span) -> bool {
+        let diagnostic = if RESERVED_TYPE_NAMES.contains(&name) {
+            Diagnostic::error(
+                codes::DUPLICATE_DEFINITION,
+                format!("the {} `{}` has the name of a builtin type", kind, name),
+                span,
+            )
+            .with_label("reserved type name".to_string())
+        } else if self.env.is_type_defined(name) {
+            Diagnostic::error(
+                codes::DUPLICATE_DEFINITION,
+                format!("the {} `{}` is defined more than once", kind, name),
+                span,
+            )
+            .with_label("redefined here".to_string())
+        } else {
+            return true;
+        };
+        self.report(diagnostic);
+        false
+    }
+
+    /// Makes the name of a record known, so that types can refer to it
+    /// before its fields are. Returns false if the name is taken.
+    fn declare_record(&mut self, record: &RecordDecl) -> bool {
+        if !self.check_type_name("record", &record.name, record.span) {
+            return false;
+        }
+        self.check_type_params(&record.type_params, record.span);
+        self.env.define_record(&record.name, Vec::new());
+        self.env
+            .define_record_params(&record.name, record.type_params.clone());
+        true
+    }
+
+    fn define_record_fields(&mut self, record: &RecordDecl) {
+        self.type_params = record.type_params.clone();
+        let mut fields: Vec<Field> = Vec::new();
+        for field in &record.fields {
+            let ty = self.resolve_type(&field.ty);
+            if fields.iter().any(|f| f.name == field.name) {
+                self.report(
+                    Diagnostic::error(
+                        codes::DUPLICATE_FIELD,
+                        format!("field `{}` is already declared", field.name),
+                        field.span,
+                    )
+                    .with_label("duplicate field".to_string()),
+                );
+                continue;
+            }
+            fields.push(Field::new(field.name.clone(), ty));
+        }
+        self.type_params.clear();
+        self.env.define_record(&record.name, fields);
+    }
+
+    /// Makes the name of an enum known, like `declare_record`. Returns false
+    /// if the name is taken.
+    fn declare_enum(&mut self, enum_decl: &EnumDecl) -> bool {
+        if !self.check_type_name("enum", &enum_decl.name, enum_decl.span) {
+            return false;
+        }
+        self.env.define_enum(&enum_decl.name, Vec::new());
+        true
+    }
+
+    fn define_variants(&mut self, enum_decl: &EnumDecl) {
+        let mut variants: Vec<Variant> = Vec::new();
+        for variant in &enum_decl.variants {
+            let mut fields: Vec<Field> = Vec::new();
+            for field in &variant.fields {
+                let ty = self.resolve_type(&field.ty);
+                if fields.iter().any(|f| f.name == field.name) {
+                    self.report(
+                        Diagnostic::error(
+                            codes::DUPLICATE_FIELD,
+                            format!("field `{}` is already declared", field.name),
+                            field.span,
+                        )
+                        .with_label("duplicate field".to_string()),
+                    );
+                    continue;
+                }
+                // the generated table stores the name of the variant in `tag`
+                if field.name == "tag" {
+                    self.report(
+                        Diagnostic::error(
+                            codes::DUPLICATE_FIELD,
+                            "the field name `tag` is reserved".to_string(),
+                            field.span,
+                        )
+                        .with_label("reserved field name".to_string())
+                        .with_note("`tag` holds the name of the variant".to_string()),
+                    );
+                    continue;
+                }
+                fields.push(Field::new(field.name.clone(), ty));
+            }
+            if variants.iter().any(|v| v.name == variant.name) {
+                self.report(
+                    Diagnostic::error(
+                        codes::DUPLICATE_DEFINITION,
+                        format!("the variant `{}` is defined more than once", variant.name),
+                        variant.span,
+                    )
+                    .with_label("redefined here".to_string()),
+                );
+                continue;
+            }
+            variants.push(Variant::new(variant.name.clone(), fields));
+        }
+        self.env.define_enum(&enum_decl.name, variants);
+    }
+
+    /// Defines the variants of an enum as values: a variant with fields is a
+    /// function that takes them, and one without is a value of the enum.
+    fn declare_variants(&mut self, enum_decl: &EnumDecl) {
+        let enum_ty = Type::Enum(enum_decl.name.clone());
+        let variants = self
+            .env
+            .get_enum(&enum_decl.name)
+            .unwrap_or_default()
+            .to_vec();
+        for variant in variants {
+            if self.env.get_type(&variant.name).is_some() {
+                let span = enum_decl
+                    .variants
+                    .iter()
+                    .find(|v| v.name == variant.name)
+                    .map_or(enum_decl.span, |v| v.span);
+                self.report(
+                    Diagnostic::error(
+                        codes::DUPLICATE_DEFINITION,
+                        format!("the variant `{}` is defined more than once", variant.name),
+                        span,
+                    )
+                    .with_label("redefined here".to_string())
+                    .with_note("variants of different enums share one namespace".to_string()),
+                );
+                continue;
+            }
+            let ty = if variant.fields.is_empty() {
+                enum_ty.clone()
             } else {
-                Err(format!(
-                    "Type mismatch: {:?} cannot be assigned to {:?}",
-                    ty_expr, ty
-                ))
-            }
-        }
-        Stmt::Return(expr) => {
-            let ty = derive_type(expr, env)?;
-            // check if the return type matches the function's return type
-            if let Some(ret) = return_type {
-                if ty == ret {
-                    Ok(())
+                let field_types = variant.fields.iter().map(|f| f.ty.clone()).collect();
+                Type::Function(field_types, Box::new(enum_ty.clone()))
+            };
+            self.env.set_type(&variant.name, ty);
+        }
+    }
+
+    /// Makes the name of an interface known, like `declare_record`. Returns
+    /// false if the name is taken.
+    fn declare_interface(&mut self, interface: &InterfaceDecl) -> bool {
+        if !self.check_type_name("interface", &interface.name, interface.span) {
+            return false;
+        }
+        self.env.define_interface(&interface.name, Vec::new());
+        true
+    }
+
+    fn define_interface_methods(&mut self, interface: &InterfaceDecl) {
+        // `Self` stays a parameter, as it is the record that implements the
+        // interface; see `interface_method`
+        self.self_type = Some(Type::Param("Self".to_string()));
+        let mut methods: Vec<Field> = Vec::new();
+        for method in &interface.methods {
+            let param_types = method.params[1..]
+                .iter()
+                .map(|param| self.resolve_type(&param.ty))
+                .collect();
+            let ret = self.resolve_type(&method.ret);
+            if methods.iter().any(|m| m.name == method.name) {
+                self.report(
+                    Diagnostic::error(
+                        codes::DUPLICATE_DEFINITION,
+                        format!("the method `{}` is defined more than once", method.name),
+                        method.span,
+                    )
+                    .with_label("redefined here".to_string()),
+                );
+                continue;
+            }
+            methods.push(Field::new(
+                method.name.clone(),
+                Type::Function(param_types, Box::new(ret)),
+            ));
+        }
+        self.self_type = None;
+        self.env.define_interface(&interface.name, methods);
+    }
+
+    /// Adds the methods of an `impl` to its record, and checks them against
+    /// the interface it implements, if any. Returns the parameter and return
+    /// types of each method, or `None` if the record doesn't exist.
+    fn declare_methods(&mut self, impl_decl: &ImplDecl) -> Option<Vec<(Vec<Type>, Type)>> {
+        let Some(fields) = self.env.get_record(&impl_decl.record) else {
+            self.report(
+                Diagnostic::error(
+                    codes::UNDEFINED_TYPE,
+                    format!("cannot find record `{}` in this scope", impl_decl.record),
+                    impl_decl.span,
+                )
+                .with_label("methods can only be added to records".to_string()),
+            );
+            return None;
+        };
+        let fields = fields.to_vec();
+        let record_params = self.env.get_record_params(&impl_decl.record).to_vec();
+        self.type_params = record_params.clone();
+        self.self_type = Some(Type::Record(
+            impl_decl.record.clone(),
+            record_params.into_iter().map(Type::Param).collect(),
+        ));
+        let mut signatures = Vec::new();
+        let mut methods: Vec<Field> = Vec::new();
+        for method in &impl_decl.methods {
+            let (param_types, ret) = self.resolve_signature(method);
+            let ty = Type::Function(param_types[1..].to_vec(), Box::new(ret.clone()));
+            signatures.push((param_types, ret));
+            let defined = self
+                .env
+                .get_methods(&impl_decl.record)
+                .unwrap_or_default()
+                .iter()
+                .chain(&methods)
+                .any(|m| m.name == method.name);
+            if defined {
+                self.report(
+                    Diagnostic::error(
+                        codes::DUPLICATE_DEFINITION,
+                        format!(
+                            "the method `{}` of `{}` is defined more than once",
+                            method.name, impl_decl.record
+                        ),
+                        method.signature_span(),
+                    )
+                    .with_label("redefined here".to_string()),
+                );
+                continue;
+            }
+            // `p.x()` has to mean either the field or the method
+            if fields.iter().any(|f| f.name == method.name) {
+                self.report(
+                    Diagnostic::error(
+                        codes::DUPLICATE_DEFINITION,
+                        format!(
+                            "`{}` is already a field of `{}`",
+                            method.name, impl_decl.record
+                        ),
+                        method.signature_span(),
+                    )
+                    .with_label("redefined here".to_string()),
+                );
+                continue;
+            }
+            methods.push(Field::new(method.name.clone(), ty));
+        }
+        self.type_params.clear();
+        self.self_type = None;
+        if let Some(interface) = &impl_decl.interface {
+            self.check_implementation(impl_decl, interface, &signatures);
+        }
+        self.env.add_methods(&impl_decl.record, methods);
+        Some(signatures)
+    }
+
+    /// Checks that the methods of an `impl Interface for Record` are the
+    /// methods of the interface, with the same types.
+    fn check_implementation(
+        &mut self,
+        impl_decl: &ImplDecl,
+        interface: &str,
+        signatures: &[(Vec<Type>, Type)],
+    ) {
+        let Some(required) = self.env.get_interface(interface) else {
+            self.report(
+                Diagnostic::error(
+                    codes::UNDEFINED_TYPE,
+                    format!("cannot find interface `{}` in this scope", interface),
+                    impl_decl.span,
+                )
+                .with_label("not found in this scope".to_string()),
+            );
+            return;
+        };
+        let record_params = self.env.get_record_params(&impl_decl.record);
+        let record = Type::Record(
+            impl_decl.record.clone(),
+            record_params.iter().cloned().map(Type::Param).collect(),
+        );
+        let required: Vec<Field> = required
+            .iter()
+            .map(|m| Field::new(m.name.clone(), interface_method(&m.ty, &record)))
+            .collect();
+        for (decl, (param_types, ret)) in impl_decl.methods.iter().zip(signatures) {
+            let span = decl.signature_span();
+            let method = Field::new(
+                decl.name.clone(),
+                Type::Function(param_types[1..].to_vec(), Box::new(ret.clone())),
+            );
+            match required.iter().find(|m| m.name == method.name) {
+                None => self.report(
+                    Diagnostic::error(
+                        codes::UNKNOWN_METHOD,
+                        format!(
+                            "method `{}` is not a member of interface `{}`",
+                            method.name, interface
+                        ),
+                        span,
+                    )
+                    .with_label(format!("not a member of `{}`", interface))
+                    .with_note(describe_methods(interface, &required)),
+                ),
+                Some(expected) if !is_same_type(&expected.ty, &method.ty) => self.report(
+                    Diagnostic::error(
+                        codes::MISMATCHED_TYPES,
+                        format!(
+                            "method `{}` has the wrong type for interface `{}`",
+                            method.name, interface
+                        ),
+                        span,
+                    )
+                    .with_label(format!("expected `{}`, found `{}`", expected.ty, method.ty)),
+                ),
+                Some(_) => {}
+            }
+        }
+        let missing: Vec<String> = required
+            .iter()
+            .filter(|m| !impl_decl.methods.iter().any(|decl| decl.name == m.name))
+            .map(|m| format!("`{}`", m.name))
+            .collect();
+        if !missing.is_empty() {
+            self.report(
+                Diagnostic::error(
+                    codes::MISSING_METHODS,
+                    format!(
+                        "not all methods of `{}` are implemented for `{}`",
+                        interface, impl_decl.record
+                    ),
+                    impl_decl.span,
+                )
+                .with_label(format!("missing {}", missing.join(", "))),
+            );
+        }
+        // the record is taken to implement the interface even if it is
+        // missing something, so its uses are not reported as well
+        self.env.add_implementation(&impl_decl.record, interface);
+    }
+
+    fn typecheck_methods(&mut self, impl_decl: &ImplDecl, signatures: Vec<(Vec<Type>, Type)>) {
+        let record_params = self.env.get_record_params(&impl_decl.record).to_vec();
+        self.type_params = record_params;
+        for (method, (param_types, ret)) in impl_decl.methods.iter().zip(signatures) {
+            self.typecheck_function_body(method, param_types, ret);
+        }
+        self.type_params.clear();
+    }
+
+    /// Adds a function to the environment and returns its parameter and
+    /// return types. `declared` and `externs` are the functions and externs
+    /// declared before it.
+    fn declare_function(
+        &mut self,
+        func: &FunctionDecl,
+        declared: &[(&FunctionDecl, (Vec<Type>, Type))],
+        externs: &[&ExternDecl],
+    ) -> (Vec<Type>, Type) {
+        if let Some((first, _)) = declared.iter().find(|(f, _)| f.name == func.name) {
+            self.report(
+                Diagnostic::error(
+                    codes::DUPLICATE_DEFINITION,
+                    format!("the function `{}` is defined more than once", func.name),
+                    func.signature_span(),
+                )
+                .with_label("redefined here".to_string())
+                .with_secondary(first.signature_span(), "first defined here".to_string()),
+            );
+        } else if externs.iter().any(|ext| ext.name == func.name) {
+            self.report(
+                Diagnostic::error(
+                    codes::DUPLICATE_DEFINITION,
+                    format!("the function `{}` is defined more than once", func.name),
+                    func.signature_span(),
+                )
+                .with_label("redefined here".to_string())
+                .with_note(extern_note(&func.name)),
+            );
+        } else if let Some(enum_name) = self.env.enum_of_variant(&func.name) {
+            let message = format!("`{}` is already a variant of `{}`", func.name, enum_name);
+            self.report(
+                Diagnostic::error(codes::DUPLICATE_DEFINITION, message, func.signature_span())
+                    .with_label("redefined here".to_string()),
+            );
+        }
+        self.check_type_params(&func.type_params, func.signature_span());
+        let (param_types, ret) = self.resolve_signature(func);
+        let ty = Type::Function(param_types.clone(), Box::new(ret.clone()));
+        if func.type_params.is_empty() {
+            self.env.set_type(&func.name, ty);
+        } else {
+            self.env.set_type(
+                &func.name,
+                Type::Generic(func.type_params.clone(), Box::new(ty)),
+            );
+        }
+        (param_types, ret)
+    }
+
+    /// Adds an extern to the environment, like `declare_function`.
+    fn typecheck_extern_decl(
+        &mut self,
+        ext: &ExternDecl,
+        functions: &[(&FunctionDecl, (Vec<Type>, Type))],
+        externs: &[&ExternDecl],
+    ) {
+        let message = format!("the extern `{}` is defined more than once", ext.name);
+        if let Some((func, _)) = functions.iter().find(|(f, _)| f.name == ext.name) {
+            self.report(
+                Diagnostic::error(codes::DUPLICATE_DEFINITION, message, ext.span)
+                    .with_label("redefined here".to_string())
+                    .with_secondary(func.signature_span(), "first defined here".to_string()),
+            );
+        } else if externs.iter().any(|e| e.name == ext.name) {
+            self.report(
+                Diagnostic::error(codes::DUPLICATE_DEFINITION, message, ext.span)
+                    .with_label("redefined here".to_string())
+                    .with_note(extern_note(&ext.name)),
+            );
+        } else if let Some(enum_name) = self.env.enum_of_variant(&ext.name) {
+            let message = format!("`{}` is already a variant of `{}`", ext.name, enum_name);
+            self.report(
+                Diagnostic::error(codes::DUPLICATE_DEFINITION, message, ext.span)
+                    .with_label("redefined here".to_string()),
+            );
+        }
+        let param_types = ext
+            .params
+            .iter()
+            .map(|param| self.resolve_type(&param.ty))
+            .collect();
+        let ret = self.resolve_type(&ext.ret);
+        if let Some(lua_name) = &ext.lua_name
+            && !is_lua_path(lua_name)
+        {
+            self.report(
+                Diagnostic::error(
+                    codes::INVALID_LUA_NAME,
+                    format!("`{}` is not a valid Lua name", lua_name),
+                    ext.span,
+                )
+                .with_note(
+                    "expected names separated by dots, such as `love.graphics.rectangle`"
+                        .to_string(),
+                ),
+            );
+        }
+        self.env
+            .set_type(&ext.name, Type::Function(param_types, Box::new(ret)));
+    }
+
+    /// Checks that games and records with methods, which are compiled to Lua
+    /// tables of the same name, don't share a name with anything else that
+    /// has a Lua name: a function, an extern, a variant or another table.
+    fn check_table_names(&mut self, decls: &[TopLevelDecl]) {
+        // the kind, the span and whether it is a table, by name
+        let mut names: HashMap<&str, (&str, Span, bool)> = HashMap::new();
+        let mut records: HashSet<&str> = HashSet::new();
+        for decl in decls {
+            let declared: Vec<(&str, &str, Span, bool)> = match decl {
+                TopLevelDecl::FunctionDecl(func) => {
+                    vec![(&func.name, "a function", func.signature_span(), false)]
+                }
+                TopLevelDecl::ExternDecl(ext) => vec![(&ext.name, "an extern", ext.span, false)],
+                TopLevelDecl::EnumDecl(enum_decl) => enum_decl
+                    .variants
+                    .iter()
+                    .map(|v| (v.name.as_str(), "a variant", v.span, false))
+                    .collect(),
+                TopLevelDecl::GameDecl(game) => vec![(&game.name, "a game", game.span, true)],
+                // only the first `impl` of a record creates its table
+                TopLevelDecl::ImplDecl(impl_decl) if records.insert(&impl_decl.record) => {
+                    vec![(
+                        &impl_decl.record,
+                        "a record with methods",
+                        impl_decl.span,
+                        true,
+                    )]
+                }
+                _ => Vec::new(),
+            };
+            for (name, kind, span, is_table) in declared {
+                let Some(&(first_kind, first_span, first_is_table)) = names.get(name) else {
+                    names.insert(name, (kind, span, is_table));
+                    continue;
+                };
+                // clashes between other names are reported where they are
+                // declared
+                if !is_table && !first_is_table {
+                    continue;
+                }
+                let error = Diagnostic::error(
+                    codes::DUPLICATE_DEFINITION,
+                    format!("`{}` is already the name of {}", name, first_kind),
+                    span,
+                )
+                .with_label("redefined here".to_string());
+                let error = if first_kind == "an extern" {
+                    error.with_note(extern_note(name))
                 } else {
-                    Err(format!(
-                        "Type mismatch: {:?} cannot be returned from function with return type {:?}",
-                        ty, ret
-                    ))
+                    error.with_secondary(first_span, "first defined here".to_string())
+                };
+                self.report(
+                    error.with_note(
+                        "games and records with methods are compiled to Lua tables of their name"
+                            .to_string(),
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Checks the callbacks of a game against the lifecycle signatures they
+    /// are called with. The callbacks are not visible to the rest of the
+    /// program.
+    fn typecheck_game_decl(&mut self, game: &GameDecl) {
+        let mut defined: Vec<&str> = Vec::new();
+        for func in &game.functions {
+            let (param_types, ret) = self.resolve_signature(func);
+            let signature_span = func.signature_span();
+            match lifecycle_signature(&func.name) {
+                None => self.report(
+                    Diagnostic::error(
+                        codes::UNKNOWN_CALLBACK,
+                        format!("`{}` is not a lifecycle callback", func.name),
+                        signature_span,
+                    )
+                    .with_label("unknown callback".to_string())
+                    .with_note(format!(
+                        "a game can define {}",
+                        LIFECYCLE_CALLBACKS
+                            .iter()
+                            .map(|name| format!("`{}`", name))
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    )),
+                ),
+                Some(expected_params) => {
+                    let expected = Type::Function(expected_params, Box::new(Type::Void));
+                    let found = Type::Function(param_types.clone(), Box::new(ret.clone()));
+                    // types that failed to resolve have been reported already
+                    let resolved = !param_types.contains(&Type::Error) && ret != Type::Error;
+                    if resolved && found != expected {
+                        self.report(
+                            Diagnostic::error(
+                                codes::CALLBACK_SIGNATURE,
+                                format!("callback `{}` has the wrong signature", func.name),
+                                signature_span,
+                            )
+                            .with_label(format!("expected `{}`, found `{}`", expected, found)),
+                        );
+                    }
                 }
-            } else {
-                Ok(())
             }
+            if defined.contains(&func.name.as_str()) {
+                self.report(
+                    Diagnostic::error(
+                        codes::DUPLICATE_DEFINITION,
+                        format!(
+                            "the callback `{}` is defined more than once in `{}`",
+                            func.name, game.name
+                        ),
+                        signature_span,
+                    )
+                    .with_label("redefined here".to_string()),
+                );
+            }
+            defined.push(&func.name);
+            self.typecheck_function_body(func, param_types, ret);
         }
-        Stmt::Expr(expr) => {
-            derive_type(expr, env)?;
-            Ok(())
+    }
+
+    /// Resolves the parameter and return types of a function. The type
+    /// parameters of a method's record stay in scope.
+    fn resolve_signature(&mut self, func: &FunctionDecl) -> (Vec<Type>, Type) {
+        let outer = self.type_params.len();
+        self.type_params.extend(func.type_params.iter().cloned());
+        let param_types = func
+            .params
+            .iter()
+            .map(|param| self.resolve_type(&param.ty))
+            .collect();
+        let ret = self.resolve_type(&func.ret);
+        self.type_params.truncate(outer);
+        (param_types, ret)
+    }
+
+    fn typecheck_function_body(&mut self, func: &FunctionDecl, param_types: Vec<Type>, ret: Type) {
+        let outer = self.type_params.len();
+        self.type_params.extend(func.type_params.iter().cloned());
+        self.typecheck_body(&func.params, param_types, &ret, &func.body);
+        self.type_params.truncate(outer);
+        // types that failed to resolve have been reported already
+        if ret != Type::Void && ret != Type::Error && !block_returns(&func.body) {
+            self.report(
+                Diagnostic::error(
+                    codes::MISSING_RETURN,
+                    format!("function `{}` may not return a value", func.name),
+                    func.signature_span(),
+                )
+                .with_secondary(func.ret.span, format!("expected `{}` on every path", ret))
+                .with_note("add a `return` at the end of the function body".to_string()),
+            );
         }
-        Stmt::If(condition, then_branch, else_branch) => {
-            let condition_ty = derive_type(condition, env)?;
-            if condition_ty != Type::Bool {
-                Err(format!(
-                    "Condition must be a boolean, got {:?}",
-                    condition_ty
-                ))
-            } else {
-                typecheck_block(&then_branch, env, None)?;
+    }
+
+    /// Checks the body of a function or a function expression. The body is
+    /// outside of any loop, even if the function expression is in one.
+    fn typecheck_body(
+        &mut self,
+        params: &[ParamDecl],
+        param_types: Vec<Type>,
+        ret: &Type,
+        body: &Block,
+    ) {
+        // The parameters are in the same scope as the body, so a `let` can't
+        // quietly hide one
+        self.env.push_scope();
+        for (param, ty) in params.iter().zip(param_types) {
+            self.declare(&param.name, ty, param.span);
+        }
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        self.typecheck_stmts(body, ret);
+        self.loop_depth = loop_depth;
+        self.env.pop_scope();
+    }
+
+    /// Checks a block in a scope of its own.
+    fn typecheck_block(&mut self, block: &Block, return_type: &Type) {
+        self.env.push_scope();
+        self.typecheck_stmts(block, return_type);
+        self.env.pop_scope();
+    }
+
+    /// Checks the statements of a block in the current scope. The first
+    /// statement that can't be reached is reported.
+    fn typecheck_stmts(&mut self, block: &Block, return_type: &Type) {
+        let mut exit: Option<&Stmt> = None;
+        let mut reported = false;
+        for stmt in &block.stmts {
+            if let Some(exit) = exit
+                && !reported
+            {
+                reported = true;
+                self.report(
+                    Diagnostic::warning(
+                        codes::UNREACHABLE_CODE,
+                        "unreachable statement".to_string(),
+                        stmt.span,
+                    )
+                    .with_label("unreachable statement".to_string())
+                    .with_secondary(
+                        exit.span,
+                        "any code following this statement is unreachable".to_string(),
+                    ),
+                );
+            }
+            self.typecheck_stmt(stmt, return_type);
+            if exit.is_none() && stmt_exits(stmt) {
+                exit = Some(stmt);
+            }
+        }
+    }
+
+    /// Defines a variable in the current scope, reporting it if the scope
+    /// already has one of that name.
+    fn declare(&mut self, name: &str, ty: Type, span: Span) {
+        if self.env.is_defined_in_scope(name) {
+            let message = format!("`{}` is already defined in this scope", name);
+            let diagnostic = match self.options.redeclaration {
+                Redeclaration::Warn => Diagnostic::warning(codes::REDECLARATION, message, span),
+                Redeclaration::Deny => Diagnostic::error(codes::REDECLARATION, message, span),
+            };
+            self.report(
+                diagnostic
+                    .with_label(format!("`{}` redeclared here", name))
+                    .with_note("the earlier definition is shadowed from here on".to_string()),
+            );
+        }
+        self.env.set_type(name, ty);
+    }
+
+    fn typecheck_stmt(&mut self, stmt: &Stmt, return_type: &Type) {
+        match &stmt.kind {
+            StmtKind::Assign(target, expr) => {
+                // look up the type of the place being assigned to
+                let ty_left = match &target.kind {
+                    ExprKind::Ident(_) | ExprKind::Field(..) | ExprKind::Index(..) => {
+                        self.derive_type(target)
+                    }
+                    _ => {
+                        self.report(
+                            Diagnostic::error(
+                                codes::INVALID_ASSIGNMENT,
+                                "invalid left-hand side of assignment".to_string(),
+                                target.span,
+                            )
+                            .with_label("cannot assign to this expression".to_string()),
+                        );
+                        Type::Error
+                    }
+                };
+                // derive the type of the expression
+                let ty_right = self.derive_type_expecting(expr, &ty_left);
+                // assignment is valid if the types are compatible
+                if !self.env.is_assignable(&ty_left, &ty_right) {
+                    let error = mismatch(&ty_left, &ty_right, expr);
+                    self.report(match &target.kind {
+                        ExprKind::Ident(id) => error
+                            .with_note(format!("`{}` was declared with type `{}`", id, ty_left)),
+                        _ => error.with_secondary(
+                            target.span,
+                            format!("this field has type `{}`", ty_left),
+                        ),
+                    });
+                }
+            }
+            StmtKind::Let(id, None, expr) => {
+                // without a type, the variable takes the type of its value
+                let mut inferred = self.derive_type(expr);
+                if mentions_nil(&inferred) {
+                    self.report(
+                        Diagnostic::error(
+                            codes::CANNOT_INFER,
+                            format!("cannot infer the type of `{}`", id),
+                            expr.span,
+                        )
+                        .with_label("`nil` does not say what type it stands in for".to_string())
+                        .with_note(format!("give `{}` a type where it is declared", id)),
+                    );
+                    inferred = Type::Error;
+                }
+                self.declare(id, inferred, stmt.span);
+            }
+            StmtKind::Let(id, Some(ty), expr) => {
+                // type of the assigned value must match the declared type
+                let declared = self.resolve_type(ty);
+                let ty_expr = self.derive_type_expecting(expr, &declared);
+                if !self.env.is_assignable(&declared, &ty_expr) {
+                    self.report(
+                        mismatch(&declared, &ty_expr, expr)
+                            .with_secondary(ty.span, "expected due to this".to_string()),
+                    );
+                }
+                // the identifier is defined from here on even if its value is
+                // wrong, so later uses are not reported as undefined
+                self.declare(id, declared, stmt.span);
+            }
+            StmtKind::Return(None) => {
+                if !self.env.is_assignable(return_type, &Type::Void) {
+                    self.report(
+                        Diagnostic::error(
+                            codes::MISMATCHED_TYPES,
+                            "mismatched types".to_string(),
+                            stmt.span,
+                        )
+                        .with_label(format!("expected `{}`, found `Void`", return_type))
+                        .with_note(format!("the enclosing function returns `{}`", return_type)),
+                    );
+                }
+            }
+            StmtKind::Return(Some(expr)) => {
+                let ty = self.derive_type_expecting(expr, return_type);
+                // check if the return type matches the function's return type
+                if !self.env.is_assignable(return_type, &ty) {
+                    self.report(
+                        mismatch(return_type, &ty, expr)
+                            .with_note(format!("the enclosing function returns `{}`", return_type)),
+                    );
+                }
+            }
+            StmtKind::Expr(expr) => {
+                // the value of an expression statement is thrown away, so it
+                // may have none
+                self.derive_type_or_void(expr);
+            }
+            StmtKind::If(condition, then_branch, else_branch) => {
+                let condition_ty = self.derive_type(condition);
+                if !self.env.is_assignable(&Type::Bool, &condition_ty) {
+                    self.report(
+                        Diagnostic::error(
+                            codes::NON_BOOL_CONDITION,
+                            "condition must be a boolean".to_string(),
+                            condition.span,
+                        )
+                        .with_label(format!("expected `Bool`, found `{}`", condition_ty)),
+                    );
+                }
+                // `x != nil` makes `x` a plain value in the `then` branch,
+                // and `x == nil` does the same in the `else` branch
+                let narrowed = self.narrowing(condition);
+                let (then_narrowed, else_narrowed) = match narrowed {
+                    Some((name, ty, true)) => (Some((name, ty)), None),
+                    Some((name, ty, false)) => (None, Some((name, ty))),
+                    None => (None, None),
+                };
+                self.typecheck_narrowed_block(then_branch, then_narrowed, return_type);
                 if let Some(else_branch) = else_branch {
-                    typecheck_block(&else_branch, env, None)?;
+                    self.typecheck_narrowed_block(else_branch, else_narrowed, return_type);
+                }
+            }
+            StmtKind::While(condition, body) => {
+                let condition_ty = self.derive_type(condition);
+                if !self.env.is_assignable(&Type::Bool, &condition_ty) {
+                    self.report(
+                        Diagnostic::error(
+                            codes::NON_BOOL_CONDITION,
+                            "condition must be a boolean".to_string(),
+                            condition.span,
+                        )
+                        .with_label(format!("expected `Bool`, found `{}`", condition_ty)),
+                    );
+                }
+                self.typecheck_loop_body(body, None, return_type);
+            }
+            StmtKind::ForRange(var, range, body) => {
+                for bound in [&range.start, &range.end] {
+                    let ty = self.derive_type(bound);
+                    if !self.env.is_assignable(&Type::Number, &ty) {
+                        self.report(
+                            mismatch(&Type::Number, &ty, bound)
+                                .with_note("the bounds of a range must be numbers".to_string()),
+                        );
+                    }
+                }
+                self.typecheck_loop_body(body, Some((var, Type::Number)), return_type);
+            }
+            StmtKind::ForEach(var, array, body) => {
+                let element_ty = match self.derive_type(array) {
+                    Type::Array(element_ty) => *element_ty,
+                    Type::Error => Type::Error,
+                    ty => {
+                        self.report(
+                            Diagnostic::error(
+                                codes::NOT_AN_ARRAY,
+                                format!("cannot iterate over a value of type `{}`", ty),
+                                array.span,
+                            )
+                            .with_label("expected an array".to_string()),
+                        );
+                        Type::Error
+                    }
+                };
+                self.typecheck_loop_body(body, Some((var, element_ty)), return_type);
+            }
+            StmtKind::Block(block) => self.typecheck_block(block, return_type),
+            StmtKind::Match(subject, arms) => self.typecheck_match(subject, arms, return_type),
+            StmtKind::Break | StmtKind::Continue => {
+                if self.loop_depth == 0 {
+                    let keyword = if matches!(stmt.kind, StmtKind::Break) {
+                        "break"
+                    } else {
+                        "continue"
+                    };
+                    self.report(
+                        Diagnostic::error(
+                            codes::OUTSIDE_OF_LOOP,
+                            format!("`{}` outside of a loop", keyword),
+                            stmt.span,
+                        )
+                        .with_label(format!("cannot `{}` outside of a loop", keyword)),
+                    );
                 }
-                Ok(())
             }
         }
     }
-}
 
-pub fn derive_type(expr: &Expr, env: &mut TypeEnvironment) -> Result<Type, String> {
-    match expr {
-        Expr::Number(_) => Ok(Type::Number),
-        Expr::String(_) => Ok(Type::String),
-        Expr::Ident(id) => match env.get_type(id) {
-            Some(ty) => Ok(ty),
-            None => Err(format!("Undefined identifier: {}", id)),
-        },
-        Expr::BinaryExp(left, opcode, right) => {
-            let left_ty = derive_type(left, env)?;
-            let right_ty = derive_type(right, env)?;
-            match opcode {
-                Opcode::Add | Opcode::Sub | Opcode::Mul | Opcode::Div => {
-                    match (&left_ty, &right_ty) {
-                        (Type::Number, Type::Number) => Ok(Type::Number),
-                        _ => Err(format!(
-                            "Type mismatch: {:?} {:?} {:?}",
-                            &left_ty, opcode, &right_ty
-                        )),
+    /// Checks the body of a loop, with the loop variable in scope if there
+    /// is one.
+    fn typecheck_loop_body(
+        &mut self,
+        body: &Block,
+        var: Option<(&String, Type)>,
+        return_type: &Type,
+    ) {
+        self.env.push_scope();
+        if let Some((name, ty)) = var {
+            self.env.set_type(name, ty);
+        }
+        self.loop_depth += 1;
+        self.typecheck_stmts(body, return_type);
+        self.loop_depth -= 1;
+        self.env.pop_scope();
+    }
+
+    /// Checks a `match`: every variant of the enum must be covered by an
+    /// arm, and each arm binds the fields it names.
+    fn typecheck_match(&mut self, subject: &Expr, arms: &[MatchArm], return_type: &Type) {
+        let subject_ty = self.derive_type(subject);
+        let variants = match &subject_ty {
+            Type::Enum(name) => Some(self.env.get_enum(name).unwrap_or_default().to_vec()),
+            Type::Error => None,
+            ty => {
+                let mut error = Diagnostic::error(
+                    codes::MISMATCHED_TYPES,
+                    format!("cannot match on a value of type `{}`", ty),
+                    subject.span,
+                )
+                .with_label("expected an enum".to_string());
+                if let Type::Optional(_) = ty {
+                    error = error.with_note(
+                        "the value may be `nil`; compare it with `nil` first".to_string(),
+                    );
+                }
+                self.report(error);
+                None
+            }
+        };
+
+        let mut covered: Vec<&str> = Vec::new();
+        let mut wildcard: Option<&MatchArm> = None;
+        for arm in arms {
+            // an arm after `_`, or for a variant that is already covered,
+            // never runs
+            let earlier = match &arm.pattern.kind {
+                PatternKind::Variant(name, _) if covered.contains(&name.as_str()) => arms
+                    .iter()
+                    .find(|a| matches!(&a.pattern.kind, PatternKind::Variant(n, _) if n == name)),
+                _ => wildcard,
+            };
+            if let Some(earlier) = earlier {
+                self.report(
+                    Diagnostic::warning(
+                        codes::UNREACHABLE_CODE,
+                        "unreachable pattern".to_string(),
+                        arm.pattern.span,
+                    )
+                    .with_label("unreachable pattern".to_string())
+                    .with_secondary(
+                        earlier.pattern.span,
+                        "matches any value this pattern matches".to_string(),
+                    ),
+                );
+            }
+
+            let mut bindings: Vec<(&String, Type)> = Vec::new();
+            match &arm.pattern.kind {
+                PatternKind::Wildcard => wildcard = wildcard.or(Some(arm)),
+                PatternKind::Variant(name, fields) => {
+                    covered.push(name);
+                    let variant = variants
+                        .as_ref()
+                        .map(|variants| variants.iter().find(|v| &v.name == name));
+                    for field in fields {
+                        let ty = match variant {
+                            Some(Some(variant)) => {
+                                match variant.fields.iter().find(|f| &f.name == field) {
+                                    Some(f) => f.ty.clone(),
+                                    None => {
+                                        let note = describe_fields(name, &variant.fields);
+                                        self.report(
+                                            Diagnostic::error(
+                                                codes::UNKNOWN_FIELD,
+                                                format!(
+                                                    "variant `{}` has no field named `{}`",
+                                                    name, field
+                                                ),
+                                                arm.pattern.span,
+                                            )
+                                            .with_label("unknown field".to_string())
+                                            .with_note(note),
+                                        );
+                                        Type::Error
+                                    }
+                                }
+                            }
+                            _ => Type::Error,
+                        };
+                        bindings.push((field, ty));
+                    }
+                    if let (Some(None), Type::Enum(enum_name)) = (variant, &subject_ty) {
+                        let names: Vec<String> = variants
+                            .iter()
+                            .flatten()
+                            .map(|v| format!("`{}`", v.name))
+                            .collect();
+                        self.report(
+                            Diagnostic::error(
+                                codes::UNKNOWN_VARIANT,
+                                format!("enum `{}` has no variant named `{}`", enum_name, name),
+                                arm.pattern.span,
+                            )
+                            .with_label("unknown variant".to_string())
+                            .with_note(format!(
+                                "`{}` has variant(s) {}",
+                                enum_name,
+                                names.join(", ")
+                            )),
+                        );
+                    }
+                }
+            }
+
+            self.env.push_scope();
+            for (name, ty) in bindings {
+                self.declare(name, ty, arm.pattern.span);
+            }
+            self.typecheck_stmts(&arm.body, return_type);
+            self.env.pop_scope();
+        }
+
+        if let (Some(variants), None) = (&variants, wildcard) {
+            let missing: Vec<String> = variants
+                .iter()
+                .filter(|v| !covered.contains(&v.name.as_str()))
+                .map(|v| format!("`{}`", v.name))
+                .collect();
+            if !missing.is_empty() {
+                self.report(
+                    Diagnostic::error(
+                        codes::NON_EXHAUSTIVE_MATCH,
+                        format!("non-exhaustive match: {} not covered", missing.join(", ")),
+                        subject.span,
+                    )
+                    .with_label(format!("{} not covered", missing.join(", ")))
+                    .with_note(
+                        "add an arm for each missing variant, or a `_` arm for the rest"
+                            .to_string(),
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Checks a branch of an `if`, with the variable its condition compares
+    /// against `nil` narrowed to its non-optional type if there is one. The
+    /// narrowed variable is in a scope around that of the branch, so the
+    /// branch can declare a variable of the same name.
+    fn typecheck_narrowed_block(
+        &mut self,
+        block: &Block,
+        narrowed: Option<(&String, Type)>,
+        return_type: &Type,
+    ) {
+        self.env.push_scope();
+        if let Some((name, ty)) = narrowed {
+            self.env.set_type(name, ty);
+        }
+        self.typecheck_block(block, return_type);
+        self.env.pop_scope();
+    }
+
+    /// If `condition` compares an optional variable with `nil`, returns the
+    /// variable, the type it has when it is not `nil`, and whether that is
+    /// when the condition holds (`!=`) or when it fails (`==`). Only plain
+    /// variables are narrowed, since a field may change between the check
+    /// and its use.
+    fn narrowing<'a>(&self, condition: &'a Expr) -> Option<(&'a String, Type, bool)> {
+        let ExprKind::BinaryExp(left, opcode, right) = &condition.kind else {
+            return None;
+        };
+        let not_nil = match opcode {
+            Opcode::Neq => true,
+            Opcode::Eq => false,
+            _ => return None,
+        };
+        let name = match (&left.kind, &right.kind) {
+            (ExprKind::Ident(name), ExprKind::Nil) | (ExprKind::Nil, ExprKind::Ident(name)) => name,
+            _ => return None,
+        };
+        match self.env.get_type(name)? {
+            Type::Optional(inner) => Some((name, *inner, not_nil)),
+            _ => None,
+        }
+    }
+
+    /// Like `derive_type`, but for a place where a value of type `expected`
+    /// is wanted. Record literals have no type of their own and take it from
+    /// what is expected.
+    fn derive_type_expecting(&mut self, expr: &Expr, expected: &Type) -> Type {
+        match (&expr.kind, expected) {
+            // a literal where an optional is expected is never `nil`
+            (ExprKind::Record(_) | ExprKind::Array(_), Type::Optional(inner)) => {
+                self.derive_type_expecting(expr, inner)
+            }
+            (ExprKind::Record(fields), Type::Record(name, args)) => {
+                self.typecheck_record_literal(expr.span, fields, name, args);
+                expected.clone()
+            }
+            (ExprKind::Call(callee, args), _) => {
+                let ty = self.derive_call(expr, callee, args, Some(expected));
+                self.expect_value(expr, ty)
+            }
+            // a generic function used as a value takes its type parameters
+            // from the function type that is expected
+            (ExprKind::Ident(name), _) if *expected != Type::Error => {
+                match self.env.get_type(name) {
+                    Some(Type::Generic(vars, ty)) => {
+                        let target = match expected {
+                            Type::Optional(inner) => inner,
+                            _ => expected,
+                        };
+                        let mut substitution = HashMap::new();
+                        unify(&ty, target, &vars, &mut substitution);
+                        if vars.iter().all(|var| substitution.contains_key(var)) {
+                            substitute(&ty, &substitution)
+                        } else {
+                            // it can't be the expected type whatever its
+                            // type parameters are, which the caller reports
+                            Type::Generic(vars, ty)
+                        }
+                    }
+                    _ => self.derive_type(expr),
+                }
+            }
+            (ExprKind::Record(fields), Type::Error) => {
+                for field in fields {
+                    self.derive_type(&field.value);
+                }
+                Type::Error
+            }
+            (ExprKind::Record(fields), _) => {
+                for field in fields {
+                    self.derive_type(&field.value);
+                }
+                self.report(
+                    Diagnostic::error(
+                        codes::MISMATCHED_TYPES,
+                        "mismatched types".to_string(),
+                        expr.span,
+                    )
+                    .with_label(format!("expected `{}`, found a record", expected)),
+                );
+                Type::Error
+            }
+            (ExprKind::Array(elements), Type::Array(element_ty)) => {
+                for element in elements {
+                    let ty = self.derive_type_expecting(element, element_ty);
+                    if !self.env.is_assignable(element_ty, &ty) {
+                        self.report(mismatch(element_ty, &ty, element));
+                    }
+                }
+                expected.clone()
+            }
+            _ => self.derive_type(expr),
+        }
+    }
+
+    /// Checks the fields of a record literal against the declaration of
+    /// `record`: every field must be given exactly once, with the right type.
+    fn typecheck_record_literal(
+        &mut self,
+        span: Span,
+        fields: &[FieldInit],
+        record: &str,
+        args: &[Type],
+    ) {
+        if self.env.get_methods(record).is_some() {
+            self.info.record_literals.insert(span, record.to_string());
+        }
+        let declared = self.env.record_fields(record, args);
+        let mut seen: Vec<&str> = Vec::new();
+        for field in fields {
+            let Some(decl) = declared.iter().find(|f| f.name == field.name) else {
+                self.derive_type(&field.value);
+                self.report(
+                    Diagnostic::error(
+                        codes::UNKNOWN_FIELD,
+                        format!("record `{}` has no field named `{}`", record, field.name),
+                        field.span,
+                    )
+                    .with_label("unknown field".to_string())
+                    .with_note(describe_fields(record, &declared)),
+                );
+                continue;
+            };
+            if seen.contains(&field.name.as_str()) {
+                self.report(
+                    Diagnostic::error(
+                        codes::DUPLICATE_FIELD,
+                        format!("field `{}` is specified more than once", field.name),
+                        field.span,
+                    )
+                    .with_label("duplicate field".to_string()),
+                );
+            }
+            seen.push(&field.name);
+            let ty = self.derive_type_expecting(&field.value, &decl.ty);
+            if !self.env.is_assignable(&decl.ty, &ty) {
+                self.report(mismatch(&decl.ty, &ty, &field.value).with_note(format!(
+                    "field `{}` of `{}` has type `{}`",
+                    decl.name, record, decl.ty
+                )));
+            }
+        }
+        let missing: Vec<String> = declared
+            .iter()
+            .filter(|f| !seen.contains(&f.name.as_str()))
+            .map(|f| format!("`{}`", f.name))
+            .collect();
+        if !missing.is_empty() {
+            self.report(
+                Diagnostic::error(
+                    codes::MISSING_FIELDS,
+                    format!(
+                        "missing field(s) {} in record `{}`",
+                        missing.join(", "),
+                        record
+                    ),
+                    span,
+                )
+                .with_label(format!("missing {}", missing.join(", "))),
+            );
+        }
+    }
+
+    /// Derives the type of an expression whose value is used, reporting any
+    /// errors in it. An expression that does not typecheck has type
+    /// `Type::Error`.
+    fn derive_type(&mut self, expr: &Expr) -> Type {
+        let ty = self.derive_type_or_void(expr);
+        self.expect_value(expr, ty)
+    }
+
+    /// Reports an expression of type `Void` where a value is needed.
+    fn expect_value(&mut self, expr: &Expr, ty: Type) -> Type {
+        match ty {
+            Type::Void => {
+                self.report(
+                    Diagnostic::error(
+                        codes::VOID_VALUE,
+                        "expression has no value".to_string(),
+                        expr.span,
+                    )
+                    .with_label("this returns `Void`".to_string()),
+                );
+                Type::Error
+            }
+            ty => ty,
+        }
+    }
+
+    /// Like `derive_type`, but for an expression whose value is not used,
+    /// such as a call to a function that returns `Void`.
+    fn derive_type_or_void(&mut self, expr: &Expr) -> Type {
+        match &expr.kind {
+            ExprKind::Record(fields) => {
+                for field in fields {
+                    self.derive_type(&field.value);
+                }
+                self.report(
+                    Diagnostic::error(
+                        codes::UNTYPED_RECORD,
+                        "cannot infer the type of this record literal".to_string(),
+                        expr.span,
+                    )
+                    .with_note(
+                        "record literals can only be used where a record type is expected"
+                            .to_string(),
+                    ),
+                );
+                Type::Error
+            }
+            ExprKind::Array(elements) => {
+                // Without an expected type, the first element decides
+                let Some((first, rest)) = elements.split_first() else {
+                    self.report(
+                        Diagnostic::error(
+                            codes::UNTYPED_ARRAY,
+                            "cannot infer the type of an empty array".to_string(),
+                            expr.span,
+                        )
+                        .with_note(
+                            "empty arrays can only be used where an array type is expected"
+                                .to_string(),
+                        ),
+                    );
+                    return Type::Error;
+                };
+                let element_ty = self.derive_type(first);
+                if let Type::Optional(_) = element_ty {
+                    self.report(optional_element_error(&element_ty, expr.span));
+                }
+                for element in rest {
+                    let ty = self.derive_type_expecting(element, &element_ty);
+                    if !self.env.is_assignable(&element_ty, &ty) {
+                        self.report(
+                            mismatch(&element_ty, &ty, element)
+                                .with_secondary(first.span, "expected due to this".to_string()),
+                        );
                     }
                 }
-                Opcode::Eq | Opcode::Neq | Opcode::Lt | Opcode::Le | Opcode::Gt | Opcode::Ge => {
-                    match (&left_ty, &right_ty) {
-                        (Type::Number, Type::Number) => Ok(Type::Bool),
-                        _ => Err(format!(
-                            "Type mismatch: {:?} {:?} {:?}",
-                            &left_ty, opcode, &right_ty
-                        )),
+                Type::Array(Box::new(element_ty))
+            }
+            ExprKind::Index(array, index) => {
+                let array_ty = self.derive_type(array);
+                let index_ty = self.derive_type(index);
+                if !self.env.is_assignable(&Type::Number, &index_ty) {
+                    self.report(
+                        mismatch(&Type::Number, &index_ty, index)
+                            .with_note("arrays are indexed by `Number`".to_string()),
+                    );
+                }
+                match array_ty {
+                    Type::Array(element_ty) => *element_ty,
+                    Type::Error => Type::Error,
+                    _ => {
+                        self.report(
+                            Diagnostic::error(
+                                codes::NOT_AN_ARRAY,
+                                format!("cannot index into a value of type `{}`", array_ty),
+                                expr.span,
+                            )
+                            .with_secondary(array.span, format!("this has type `{}`", array_ty)),
+                        );
+                        Type::Error
+                    }
+                }
+            }
+            ExprKind::Field(record, field) => {
+                let record_ty = self.derive_type(record);
+                self.derive_field(expr, record, &record_ty, field)
+            }
+            ExprKind::Number(_) => Type::Number,
+            ExprKind::String(_) => Type::String,
+            ExprKind::Bool(_) => Type::Bool,
+            ExprKind::Nil => Type::Nil,
+            ExprKind::Ident(id) => match self.env.get_type(id) {
+                Some(Type::Generic(vars, ty)) => {
+                    self.instantiate(expr, id, &vars, &ty, HashMap::new())
+                }
+                Some(ty) => ty,
+                None => {
+                    self.report(
+                        Diagnostic::error(
+                            codes::UNDEFINED_IDENTIFIER,
+                            format!("cannot find value `{}` in this scope", id),
+                            expr.span,
+                        )
+                        .with_label("not found in this scope".to_string()),
+                    );
+                    Type::Error
+                }
+            },
+            ExprKind::UnaryExp(op, operand) => {
+                let operand_ty = self.derive_type(operand);
+                match unary_result(op, &operand_ty) {
+                    Some(ty) => ty,
+                    // the operand is already wrong; don't report it twice
+                    None if operand_ty == Type::Error => Type::Error,
+                    None => {
+                        self.report(
+                            Diagnostic::error(
+                                codes::INVALID_OPERANDS,
+                                format!("cannot apply `{:?}` to `{}`", op, operand_ty),
+                                expr.span,
+                            )
+                            .with_secondary(operand.span, format!("`{}`", operand_ty)),
+                        );
+                        Type::Error
+                    }
+                }
+            }
+            ExprKind::BinaryExp(left, Opcode::Coalesce, right) => {
+                let left_ty = self.derive_type(left);
+                match left_ty {
+                    Type::Optional(inner) => {
+                        // a type parameter may stand for `Bool`
+                        if matches!(*inner, Type::Bool | Type::Param(_)) {
+                            self.info.false_defaults.insert(expr.span);
+                        }
+                        // the default may itself be optional, in which case
+                        // so is the result
+                        let right_ty = self.derive_type_expecting(right, &inner);
+                        if self.env.is_assignable(&inner, &right_ty) {
+                            *inner
+                        } else if self
+                            .env
+                            .is_assignable(&Type::Optional(inner.clone()), &right_ty)
+                        {
+                            Type::Optional(inner)
+                        } else {
+                            self.report(
+                                mismatch(&inner, &right_ty, right).with_secondary(
+                                    left.span,
+                                    format!("this has type `{}?`", inner),
+                                ),
+                            );
+                            Type::Error
+                        }
+                    }
+                    Type::Error => {
+                        self.derive_type(right);
+                        Type::Error
+                    }
+                    _ => {
+                        self.derive_type(right);
+                        self.report(
+                            Diagnostic::error(
+                                codes::INVALID_OPERANDS,
+                                format!("cannot apply `??` to a value of type `{}`", left_ty),
+                                expr.span,
+                            )
+                            .with_secondary(left.span, format!("`{}` is never `nil`", left_ty))
+                            .with_note("`??` needs an optional value on its left".to_string()),
+                        );
+                        Type::Error
                     }
                 }
             }
+            ExprKind::BinaryExp(left, opcode, right) => {
+                let left_ty = self.derive_type(left);
+                let right_ty = self.derive_type(right);
+                match (&left_ty, &right_ty) {
+                    // an operand is already wrong; don't report it twice
+                    (Type::Error, _) | (_, Type::Error) => Type::Error,
+                    _ => match binary_result(opcode, &left_ty, &right_ty) {
+                        Some(ty) => ty,
+                        None => {
+                            self.report(invalid_operands(
+                                opcode,
+                                (left, &left_ty),
+                                (right, &right_ty),
+                            ));
+                            Type::Error
+                        }
+                    },
+                }
+            }
+            ExprKind::Lambda(params, ret, body) => {
+                let param_types: Vec<Type> = params
+                    .iter()
+                    .map(|param| self.resolve_type(&param.ty))
+                    .collect();
+                let ret_ty = self.resolve_type(ret);
+                self.typecheck_body(params, param_types.clone(), &ret_ty, body);
+                if ret_ty != Type::Void && ret_ty != Type::Error && !block_returns(body) {
+                    self.report(
+                        Diagnostic::error(
+                            codes::MISSING_RETURN,
+                            "function expression may not return a value".to_string(),
+                            Span::new(expr.span.start, ret.span.end),
+                        )
+                        .with_secondary(ret.span, format!("expected `{}` on every path", ret_ty))
+                        .with_note("add a `return` at the end of the function body".to_string()),
+                    );
+                }
+                Type::Function(param_types, Box::new(ret_ty))
+            }
+            ExprKind::Call(callee, args) => self.derive_call(expr, callee, args, None),
         }
-        Expr::Call(func_name, args) => match env.get_type(func_name) {
-            Some(Type::Function(arg_types, ret_type)) => {
+    }
+
+    /// Derives the type of the field `field` of `record`, whose type has
+    /// been derived as `record_ty`.
+    fn derive_field(&mut self, expr: &Expr, record: &Expr, record_ty: &Type, field: &str) -> Type {
+        match record_ty {
+            Type::Error => Type::Error,
+            Type::Record(name, args) => {
+                let fields = self.env.record_fields(name, args);
+                let methods = self.env.record_methods(name, args);
+                match fields.iter().find(|f| f.name == field) {
+                    Some(f) => f.ty.clone(),
+                    None if methods.iter().any(|m| m.name == field) => {
+                        self.report(
+                            Diagnostic::error(
+                                codes::METHOD_VALUE,
+                                format!("method `{}` of `{}` must be called", field, name),
+                                expr.span,
+                            )
+                            .with_label("method not called".to_string())
+                            .with_note("methods can't be used as values".to_string()),
+                        );
+                        Type::Error
+                    }
+                    None => {
+                        let note = describe_fields(name, &fields);
+                        let mut error = Diagnostic::error(
+                            codes::UNKNOWN_FIELD,
+                            format!("record `{}` has no field named `{}`", name, field),
+                            expr.span,
+                        )
+                        .with_label("unknown field".to_string())
+                        .with_note(note);
+                        if !methods.is_empty() {
+                            error = error.with_note(describe_methods(name, &methods));
+                        }
+                        self.report(error);
+                        Type::Error
+                    }
+                }
+            }
+            Type::Interface(name) => {
+                let methods = self.env.get_interface(name).unwrap_or_default();
+                if methods.iter().any(|m| m.name == field) {
+                    self.report(
+                        Diagnostic::error(
+                            codes::METHOD_VALUE,
+                            format!("method `{}` of `{}` must be called", field, name),
+                            expr.span,
+                        )
+                        .with_label("method not called".to_string())
+                        .with_note("methods can't be used as values".to_string()),
+                    );
+                } else {
+                    let note = describe_methods(name, methods);
+                    self.report(
+                        Diagnostic::error(
+                            codes::UNKNOWN_METHOD,
+                            format!("interface `{}` has no method named `{}`", name, field),
+                            expr.span,
+                        )
+                        .with_label("unknown method".to_string())
+                        .with_note(note),
+                    );
+                }
+                Type::Error
+            }
+            _ => {
+                let mut error = Diagnostic::error(
+                    codes::NOT_A_RECORD,
+                    format!("type `{}` has no fields", record_ty),
+                    expr.span,
+                )
+                .with_secondary(record.span, format!("this has type `{}`", record_ty));
+                if let Type::Optional(_) = record_ty {
+                    error = error.with_note(
+                        "the value may be `nil`; compare it with `nil` first".to_string(),
+                    );
+                }
+                self.report(error);
+                Type::Error
+            }
+        }
+    }
+
+    /// The type of the method `name` of a value of type `ty`, without its
+    /// `self` parameter, if it has such a method.
+    fn method_type(&self, ty: &Type, name: &str) -> Option<Type> {
+        match ty {
+            Type::Interface(interface) => self
+                .env
+                .get_interface(interface)?
+                .iter()
+                .find(|m| m.name == name)
+                .map(|m| interface_method(&m.ty, ty)),
+            // a method never has the name of a field, so `p.x()` is not
+            // ambiguous
+            Type::Record(record, args) => self
+                .env
+                .record_methods(record, args)
+                .into_iter()
+                .find(|m| m.name == name)
+                .map(|m| m.ty),
+            _ => None,
+        }
+    }
+
+    /// Derives the type of a call. The type parameters of a generic function
+    /// are inferred from the arguments, and then from the type the result is
+    /// `expected` to have, if that is known.
+    fn derive_call(
+        &mut self,
+        expr: &Expr,
+        callee: &Expr,
+        args: &[Expr],
+        expected: Option<&Type>,
+    ) -> Type {
+        // A name that isn't defined is reported as a missing function
+        // rather than a missing value
+        let (mut func_ty, func_name) = match &callee.kind {
+            ExprKind::Ident(name) => (self.env.get_type(name), format!("`{}`", name)),
+            // `d.draw()` calls a method if `d` has one, and otherwise the
+            // function in the field `draw`
+            ExprKind::Field(receiver, name) => {
+                let receiver_ty = self.derive_type(receiver);
+                match self.method_type(&receiver_ty, name) {
+                    Some(ty) => {
+                        self.info.method_calls.insert(expr.span);
+                        (Some(ty), format!("`{}`", name))
+                    }
+                    None => (
+                        Some(self.derive_field(callee, receiver, &receiver_ty, name)),
+                        "this expression".to_string(),
+                    ),
+                }
+            }
+            _ => (
+                Some(self.derive_type(callee)),
+                "this expression".to_string(),
+            ),
+        };
+        // A generic function is given the types its type parameters stand
+        // for in this call
+        if let Some(Type::Generic(vars, ty)) = &func_ty
+            && let Type::Function(param_types, ret) = &**ty
+            && param_types.len() == args.len()
+        {
+            let (arg_types, substitution) =
+                self.derive_generic_args(args, param_types, ret, vars, expected);
+            let name = match &callee.kind {
+                ExprKind::Ident(name) => name.as_str(),
+                _ => "this expression",
+            };
+            func_ty = Some(self.instantiate(expr, name, vars, ty, substitution));
+            return self.check_call(expr, &func_name, func_ty, args, arg_types);
+        }
+        if let Some(Type::Generic(_, ty)) = func_ty {
+            func_ty = Some(*ty);
+        }
+        let arg_types: Vec<Type> = match &func_ty {
+            Some(Type::Function(param_types, _)) if param_types.len() == args.len() => args
+                .iter()
+                .zip(param_types)
+                .map(|(arg, ty)| self.derive_type_expecting(arg, ty))
+                .collect(),
+            _ => args.iter().map(|arg| self.derive_type(arg)).collect(),
+        };
+        self.check_call(expr, &func_name, func_ty, args, arg_types)
+    }
+
+    /// Checks the arguments of a call, whose types have been derived, against
+    /// the type of the function. Returns the type of the result.
+    fn check_call(
+        &mut self,
+        expr: &Expr,
+        func_name: &str,
+        func_ty: Option<Type>,
+        args: &[Expr],
+        arg_types: Vec<Type>,
+    ) -> Type {
+        match func_ty {
+            Some(Type::Function(param_types, ret_type)) => {
                 // check if argument types match function parameter types
-                if arg_types.len() != args.len() {
-                    Err(format!(
-                        "Argument count mismatch: {:?} {:?}",
-                        func_name, args
-                    ))
+                if param_types.len() != args.len() {
+                    self.report(Diagnostic::error(
+                        codes::ARGUMENT_COUNT,
+                        format!(
+                            "function {} takes {} argument(s) but {} were supplied",
+                            func_name,
+                            param_types.len(),
+                            args.len()
+                        ),
+                        expr.span,
+                    ));
                 } else {
-                    for (arg, ty) in args.iter().zip(arg_types.iter()) {
-                        if !is_assignable(ty, &derive_type(arg, env)?) {
-                            return Err(format!(
-                                "Type mismatch: {:?} {:?}",
+                    for ((arg, arg_ty), ty) in args.iter().zip(&arg_types).zip(&param_types) {
+                        if !self.env.is_assignable(ty, arg_ty) {
+                            self.report(mismatch(ty, arg_ty, arg).with_note(format!(
+                                "{} has type `{}`",
                                 func_name,
-                                env.get_type(func_name)
-                            ));
+                                Type::Function(param_types.clone(), ret_type.clone())
+                            )));
                         }
                     }
-                    Ok((*ret_type).clone())
                 }
+                // the declared return type is still known, so the
+                // caller can go on checking against it
+                *ret_type
+            }
+            Some(Type::Error) => Type::Error,
+            Some(ty) => {
+                self.report(
+                    Diagnostic::error(
+                        codes::NOT_A_FUNCTION,
+                        format!("{} is not a function", func_name),
+                        expr.span,
+                    )
+                    .with_label(format!("{} has type `{}`", func_name, ty)),
+                );
+                Type::Error
+            }
+            None => {
+                self.report(
+                    Diagnostic::error(
+                        codes::UNDEFINED_FUNCTION,
+                        format!("cannot find function {} in this scope", func_name),
+                        expr.span,
+                    )
+                    .with_label("not found in this scope".to_string()),
+                );
+                Type::Error
+            }
+        }
+    }
+
+    /// Derives the types of the arguments of a call to a generic function,
+    /// working out its type parameters on the way. Record and array
+    /// literals take their type from the parameter, so they are checked
+    /// once the other arguments have fixed the type parameters. If that
+    /// leaves the parameter unknown, an array literal that can has its own
+    /// type worked out, and the rest are left to `instantiate` to report.
+    fn derive_generic_args(
+        &mut self,
+        args: &[Expr],
+        param_types: &[Type],
+        ret: &Type,
+        vars: &[String],
+        expected: Option<&Type>,
+    ) -> (Vec<Type>, HashMap<String, Type>) {
+        let mut substitution = HashMap::new();
+        let mut arg_types = vec![Type::Error; args.len()];
+        let mut deferred = Vec::new();
+        for (i, (arg, param)) in args.iter().zip(param_types).enumerate() {
+            if !mentions(param, vars) {
+                arg_types[i] = self.derive_type_expecting(arg, param);
+            } else if matches!(arg.kind, ExprKind::Record(_) | ExprKind::Array(_)) {
+                deferred.push(i);
+            } else {
+                arg_types[i] = self.derive_type(arg);
+                unify(param, &arg_types[i], vars, &mut substitution);
+            }
+        }
+        if let Some(expected) = expected {
+            unify(ret, expected, vars, &mut substitution);
+        }
+        for i in deferred {
+            let param = substitute(&param_types[i], &substitution);
+            arg_types[i] = if !mentions(&param, vars) {
+                self.derive_type_expecting(&args[i], &param)
+            } else if has_own_type(&args[i]) {
+                self.derive_type(&args[i])
+            } else {
+                let mut known = substitution.clone();
+                for var in vars {
+                    known.entry(var.clone()).or_insert(Type::Error);
+                }
+                self.derive_type_expecting(&args[i], &substitute(&param, &known))
+            };
+            unify(&param_types[i], &arg_types[i], vars, &mut substitution);
+        }
+        (arg_types, substitution)
+    }
+
+    /// Gives a generic function the plain function type its type parameters
+    /// stand for in `substitution`. Parameters that could not be inferred
+    /// are reported.
+    fn instantiate(
+        &mut self,
+        expr: &Expr,
+        name: &str,
+        vars: &[String],
+        ty: &Type,
+        mut substitution: HashMap<String, Type>,
+    ) -> Type {
+        let unknown: Vec<String> = vars
+            .iter()
+            .filter(|var| !substitution.contains_key(*var))
+            .map(|var| format!("`{}`", var))
+            .collect();
+        if !unknown.is_empty() {
+            self.report(
+                Diagnostic::error(
+                    codes::CANNOT_INFER,
+                    format!(
+                        "cannot infer the type parameter(s) {} of `{}`",
+                        unknown.join(", "),
+                        name
+                    ),
+                    expr.span,
+                )
+                .with_label("cannot infer type".to_string())
+                .with_note(
+                    "use it where its type is known, such as in a `let` with a type".to_string(),
+                ),
+            );
+            for var in vars {
+                substitution.entry(var.clone()).or_insert(Type::Error);
             }
-            Some(_) => Err(format!(
-                "Type mismatch: {:?} {:?}",
-                func_name,
-                env.get_type(func_name)
-            )),
-            None => Err(format!("Undefined function: {}", func_name)),
-        },
+        }
+        let instance = substitute(ty, &substitution);
+        // the declared signature has no optional elements, so any come from
+        // the type arguments
+        if let Some(element) = self.env.optional_element(&instance) {
+            self.report(
+                optional_element_error(&element, expr.span)
+                    .with_note(format!("`{}` has type `{}` here", name, instance)),
+            );
+            return Type::Error;
+        }
+        instance
     }
 }
 
-fn is_assignable(ty_left: &Type, ty_right: &Type) -> bool {
-    match (ty_left, ty_right) {
-        (Type::Number, Type::Number) => true,
+/// Whether a block returns on every path through it. Loops are not assumed
+/// to run, so a `return` in a loop body doesn't count.
+fn block_returns(block: &Block) -> bool {
+    block.stmts.iter().any(stmt_returns)
+}
+
+fn stmt_returns(stmt: &Stmt) -> bool {
+    match &stmt.kind {
+        StmtKind::Return(_) => true,
+        StmtKind::If(_, then_branch, Some(else_branch)) => {
+            block_returns(then_branch) && block_returns(else_branch)
+        }
+        StmtKind::Block(block) => block_returns(block),
+        // a match that doesn't cover every variant is an error of its own
+        StmtKind::Match(_, arms) => {
+            !arms.is_empty() && arms.iter().all(|arm| block_returns(&arm.body))
+        }
         _ => false,
     }
 }
+
+/// Whether control never reaches the statement after `stmt`.
+fn stmt_exits(stmt: &Stmt) -> bool {
+    matches!(stmt.kind, StmtKind::Break | StmtKind::Continue) || stmt_returns(stmt)
+}
+
+/// The error for an array whose elements may be `nil`.
+fn optional_element_error(element: &Type, span: Span) -> Diagnostic {
+    Diagnostic::error(
+        codes::OPTIONAL_ELEMENT,
+        "arrays cannot hold optional values".to_string(),
+        span,
+    )
+    .with_label(format!("elements of type `{}` may be `nil`", element))
+    .with_note("a Lua array ends at its first `nil`".to_string())
+}
+
+/// The note on a name that an earlier extern already has. That extern may
+/// come from an imported interface, which has no source to point at.
+fn extern_note(name: &str) -> String {
+    format!(
+        "`{}` is already declared by an `extern`, possibly one from an import or the prelude",
+        name
+    )
+}
+
+/// Type names that are resolved before any declared type, so a record, enum
+/// or interface can't have them.
+const RESERVED_TYPE_NAMES: &[&str] = &["Void", "Number", "String", "Bool", "Self"];
+
+/// The callbacks a game can define, named after the LÖVE callbacks.
+const LIFECYCLE_CALLBACKS: &[&str] = &[
+    "load",
+    "update",
+    "draw",
+    "keypressed",
+    "keyreleased",
+    "textinput",
+    "mousepressed",
+    "mousereleased",
+    "mousemoved",
+    "wheelmoved",
+    "focus",
+    "resize",
+];
+
+/// The parameter types of a lifecycle callback, or `None` if there is no
+/// callback of that name. All callbacks return `Void`. The host may pass
+/// more arguments than these, which Lua drops.
+fn lifecycle_signature(name: &str) -> Option<Vec<Type>> {
+    let params = match name {
+        "load" | "draw" => vec![],
+        "update" => vec![Type::Number],
+        "keypressed" | "keyreleased" | "textinput" => vec![Type::String],
+        "mousepressed" | "mousereleased" => vec![Type::Number, Type::Number, Type::Number],
+        "mousemoved" => vec![Type::Number, Type::Number, Type::Number, Type::Number],
+        "wheelmoved" | "resize" => vec![Type::Number, Type::Number],
+        "focus" => vec![Type::Bool],
+        _ => return None,
+    };
+    Some(params)
+}
+
+/// The type of applying a unary operator, or `None` if the operator is not
+/// defined for the operand.
+fn unary_result(op: &UnaryOp, operand: &Type) -> Option<Type> {
+    match (op, operand) {
+        (UnaryOp::Neg, Type::Number) => Some(Type::Number),
+        (UnaryOp::Not, Type::Bool) => Some(Type::Bool),
+        (UnaryOp::Len, Type::Array(_) | Type::String) => Some(Type::Number),
+        _ => None,
+    }
+}
+
+/// The type of applying a binary operator, or `None` if the operator is not
+/// defined for the operands.
+fn binary_result(opcode: &Opcode, left: &Type, right: &Type) -> Option<Type> {
+    match (opcode, left, right) {
+        (
+            Opcode::Add | Opcode::Sub | Opcode::Mul | Opcode::Div | Opcode::Mod | Opcode::Pow,
+            Type::Number,
+            Type::Number,
+        ) => Some(Type::Number),
+        // Lua converts numbers to strings when concatenating
+        (Opcode::Concat, Type::String | Type::Number, Type::String | Type::Number) => {
+            Some(Type::String)
+        }
+        (Opcode::Lt | Opcode::Le | Opcode::Gt | Opcode::Ge, Type::Number, Type::Number) => {
+            Some(Type::Bool)
+        }
+        // an optional is compared with `nil`, or with a value of its type
+        (Opcode::Eq | Opcode::Neq, Type::Optional(_), Type::Nil)
+        | (Opcode::Eq | Opcode::Neq, Type::Nil, Type::Optional(_)) => Some(Type::Bool),
+        (Opcode::Eq | Opcode::Neq, _, _)
+            if matches!(non_nil(left), Type::Number | Type::String | Type::Bool)
+                && non_nil(left) == non_nil(right) =>
+        {
+            Some(Type::Bool)
+        }
+        (Opcode::And | Opcode::Or, Type::Bool, Type::Bool) => Some(Type::Bool),
+        _ => None,
+    }
+}
+
+/// `T?`. Lua has a single `nil`, so an optional optional is the same as an
+/// optional.
+fn optional(ty: Type) -> Type {
+    match ty {
+        Type::Optional(_) | Type::Error => ty,
+        ty => Type::Optional(Box::new(ty)),
+    }
+}
+
+/// The type of a value once it is known not to be `nil`.
+fn non_nil(ty: &Type) -> &Type {
+    match ty {
+        Type::Optional(inner) => inner,
+        ty => ty,
+    }
+}
+
+/// Whether a type is, or is built from, the type of `nil`.
+fn mentions_nil(ty: &Type) -> bool {
+    match ty {
+        Type::Nil => true,
+        Type::Array(inner) => mentions_nil(inner),
+        _ => false,
+    }
+}
+
+/// Whether a literal has a type without one being expected: an array does
+/// if its first element does, and a record literal never.
+fn has_own_type(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Record(_) => false,
+        ExprKind::Array(elements) => elements.first().is_some_and(has_own_type),
+        _ => true,
+    }
+}
+
+/// Whether a type mentions any of the type parameters `vars`.
+fn mentions(ty: &Type, vars: &[String]) -> bool {
+    match ty {
+        Type::Param(name) => vars.contains(name),
+        Type::Array(inner) | Type::Optional(inner) => mentions(inner, vars),
+        Type::Function(params, ret) => {
+            params.iter().any(|p| mentions(p, vars)) || mentions(ret, vars)
+        }
+        Type::Record(_, args) => args.iter().any(|a| mentions(a, vars)),
+        _ => false,
+    }
+}
+
+/// The type of an interface method for a value of type `self_type`, with
+/// that type put in for `Self`.
+fn interface_method(ty: &Type, self_type: &Type) -> Type {
+    substitute(
+        ty,
+        &HashMap::from([("Self".to_string(), self_type.clone())]),
+    )
+}
+
+/// Puts the types in `substitution` in for the type parameters they belong
+/// to.
+fn substitute(ty: &Type, substitution: &HashMap<String, Type>) -> Type {
+    match ty {
+        Type::Param(name) => substitution
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| ty.clone()),
+        Type::Array(inner) => Type::Array(Box::new(substitute(inner, substitution))),
+        Type::Optional(inner) => optional(substitute(inner, substitution)),
+        Type::Function(params, ret) => Type::Function(
+            params.iter().map(|p| substitute(p, substitution)).collect(),
+            Box::new(substitute(ret, substitution)),
+        ),
+        Type::Record(name, args) => Type::Record(
+            name.clone(),
+            args.iter().map(|a| substitute(a, substitution)).collect(),
+        ),
+        _ => ty.clone(),
+    }
+}
+
+/// Matches `param`, which mentions the type parameters `vars`, against the
+/// type `arg` of a value given for it, and adds what the type parameters
+/// stand for to `substitution`. Parameters that are already known are left
+/// as they are; if they don't fit, that is found when the argument is
+/// checked against the parameter.
+fn unify(param: &Type, arg: &Type, vars: &[String], substitution: &mut HashMap<String, Type>) {
+    match (param, arg) {
+        // `nil` says nothing about the type it stands in for
+        (_, Type::Error | Type::Nil) => {}
+        (Type::Param(name), _) if vars.contains(name) => {
+            substitution
+                .entry(name.clone())
+                .or_insert_with(|| arg.clone());
+        }
+        (Type::Array(param), Type::Array(arg)) | (Type::Optional(param), Type::Optional(arg)) => {
+            unify(param, arg, vars, substitution)
+        }
+        (Type::Optional(param), arg) => unify(param, arg, vars, substitution),
+        (Type::Function(params, ret), Type::Function(arg_params, arg_ret))
+            if params.len() == arg_params.len() =>
+        {
+            for (param, arg) in params.iter().zip(arg_params) {
+                unify(param, arg, vars, substitution);
+            }
+            unify(ret, arg_ret, vars, substitution);
+        }
+        (Type::Record(name, params), Type::Record(arg_name, args)) if name == arg_name => {
+            for (param, arg) in params.iter().zip(args) {
+                unify(param, arg, vars, substitution);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn describe_methods(name: &str, methods: &[Field]) -> String {
+    if methods.is_empty() {
+        format!("`{}` has no methods", name)
+    } else {
+        let names: Vec<String> = methods.iter().map(|m| format!("`{}`", m.name)).collect();
+        format!("`{}` has method(s) {}", name, names.join(", "))
+    }
+}
+
+fn describe_fields(record: &str, fields: &[Field]) -> String {
+    if fields.is_empty() {
+        format!("`{}` has no fields", record)
+    } else {
+        let names: Vec<String> = fields.iter().map(|f| format!("`{}`", f.name)).collect();
+        format!("`{}` has field(s) {}", record, names.join(", "))
+    }
+}
+
+fn mismatch(expected: &Type, found: &Type, expr: &Expr) -> Diagnostic {
+    Diagnostic::error(
+        codes::MISMATCHED_TYPES,
+        "mismatched types".to_string(),
+        expr.span,
+    )
+    .with_label(format!("expected `{}`, found `{}`", expected, found))
+}
+
+fn invalid_operands(opcode: &Opcode, left: (&Expr, &Type), right: (&Expr, &Type)) -> Diagnostic {
+    Diagnostic::error(
+        codes::INVALID_OPERANDS,
+        format!(
+            "cannot apply `{:?}` to `{}` and `{}`",
+            opcode, left.1, right.1
+        ),
+        left.0.span.to(right.0.span),
+    )
+    .with_secondary(left.0.span, format!("`{}`", left.1))
+    .with_secondary(right.0.span, format!("`{}`", right.1))
+}
+
+/// Whether two types are the same, where an error inside either of them
+/// matches anything, so that it isn't reported again.
+fn is_same_type(ty_left: &Type, ty_right: &Type) -> bool {
+    match (ty_left, ty_right) {
+        (Type::Error, _) | (_, Type::Error) => true,
+        (Type::Array(left), Type::Array(right)) | (Type::Optional(left), Type::Optional(right)) => {
+            is_same_type(left, right)
+        }
+        (Type::Function(left_params, left_ret), Type::Function(right_params, right_ret)) => {
+            left_params.len() == right_params.len()
+                && left_params
+                    .iter()
+                    .zip(right_params)
+                    .all(|(left, right)| is_same_type(left, right))
+                && is_same_type(left_ret, right_ret)
+        }
+        (Type::Record(left, left_args), Type::Record(right, right_args)) => {
+            left == right
+                && left_args
+                    .iter()
+                    .zip(right_args)
+                    .all(|(left, right)| is_same_type(left, right))
+        }
+        _ => ty_left == ty_right,
+    }
+}
@@ -0,0 +1,166 @@
+//! Golden-file tests for the compiler.
+//!
+//! Every `tests/golden/NAME.pho` is compiled and the results are compared with
+//! the files next to it:
+//!
+//! - `NAME.lua`: the generated Lua, if compilation succeeds;
+//! - `NAME.stderr`: the diagnostics, if there are any;
+//! - `NAME.stdout`: what the generated Lua prints when run, followed by
+//!   `NAME.driver.lua` if that exists. `NAME.prelude.lua` is run first if it
+//!   exists, to stand in for the host. This is only checked when a `lua`
+//!   binary is on the `PATH`.
+//!
+//! Extra command-line arguments for the compiler can be given, separated by
+//! whitespace, in `NAME.args`.
+//!
+//! A missing expectation file means the output must be empty. Run with
+//! `PHOBOS_BLESS=1` to overwrite the expectations with the current output.
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+#[test]
+fn golden_files() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let bless = env::var_os("PHOBOS_BLESS").is_some();
+    let lua = find_lua();
+    if lua.is_none() {
+        eprintln!("note: no `lua` on the PATH, generated code will not be run");
+    }
+
+    let mut fixtures: Vec<PathBuf> = fs::read_dir(root.join("tests/golden"))
+        .expect("tests/golden should exist")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "pho"))
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty(), "no fixtures in tests/golden");
+
+    let mut failures = Vec::new();
+    for fixture in &fixtures {
+        // Compile from the crate root so that file names in diagnostics are
+        // the same on every machine
+        let relative = fixture.strip_prefix(root).unwrap();
+        let args = fs::read_to_string(fixture.with_extension("args")).unwrap_or_default();
+        let output = Command::new(env!("CARGO_BIN_EXE_phobos"))
+            .args(args.split_whitespace())
+            .arg(relative)
+            .current_dir(root)
+            .output()
+            .expect("failed to run phobos");
+        let lua_code = String::from_utf8(output.stdout).unwrap();
+        let stderr = String::from_utf8(output.stderr).unwrap();
+
+        if output.status.success() == stderr.contains("error[") {
+            failures.push(format!(
+                "{}: exit status {} does not match the diagnostics",
+                relative.display(),
+                output.status
+            ));
+        }
+        check(
+            &fixture.with_extension("lua"),
+            &lua_code,
+            bless,
+            &mut failures,
+        );
+        check(
+            &fixture.with_extension("stderr"),
+            &stderr,
+            bless,
+            &mut failures,
+        );
+
+        if let Some(lua) = &lua
+            && output.status.success()
+        {
+            let driver = fixture.with_extension("driver.lua");
+            let prelude = fixture.with_extension("prelude.lua");
+            let mut program = fs::read_to_string(&prelude).unwrap_or_default();
+            program.push_str(&lua_code);
+            if let Ok(driver) = fs::read_to_string(&driver) {
+                program.push('\n');
+                program.push_str(&driver);
+            }
+            match run_lua(lua, &program) {
+                Ok(stdout) => check(
+                    &fixture.with_extension("stdout"),
+                    &stdout,
+                    bless,
+                    &mut failures,
+                ),
+                Err(error) => failures.push(format!("{}: {}", relative.display(), error)),
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        panic!(
+            "{} golden file mismatch(es); rerun with PHOBOS_BLESS=1 to accept\n\n{}",
+            failures.len(),
+            failures.join("\n\n")
+        );
+    }
+}
+
+/// Compares `actual` with the contents of `expected`, which counts as empty
+/// if it doesn't exist.
+fn check(expected: &Path, actual: &str, bless: bool, failures: &mut Vec<String>) {
+    let contents = fs::read_to_string(expected).unwrap_or_default();
+    if contents == actual {
+        return;
+    }
+    if bless {
+        if actual.is_empty() {
+            fs::remove_file(expected).unwrap();
+        } else {
+            fs::write(expected, actual).unwrap();
+        }
+        return;
+    }
+    failures.push(format!(
+        "{} differs\n--- expected\n{}--- actual\n{}",
+        expected.display(),
+        contents,
+        actual
+    ));
+}
+
+fn find_lua() -> Option<String> {
+    ["lua", "lua5.4"].into_iter().map(String::from).find(|lua| {
+        Command::new(lua)
+            .arg("-v")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok()
+    })
+}
+
+fn run_lua(lua: &str, program: &str) -> Result<String, String> {
+    let mut child = Command::new(lua)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(program.as_bytes())
+        .map_err(|e| e.to_string())?;
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(String::from_utf8(output.stdout).unwrap())
+    } else {
+        Err(format!(
+            "generated Lua failed to run:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        ))
+    }
+}
@@ -0,0 +1,3 @@
+print(inc(41))
+print(add(2, 3))
+print(mean(1, 2))
@@ -0,0 +1,10 @@
+function inc(n)
+    return (n+1)
+end
+function add(a, n)
+    return (a+n)
+end
+function mean(a, b)
+    local sum = (a+b)
+    return (sum/2)
+end
@@ -0,0 +1,6 @@
+print(sum({1, 2, 3, 4}), sum({}))
+local ps = points()
+print(#ps, ps[2].y, ps[3].x)
+local g = grid()
+print(g[1][2], g[2][1], #g)
+print(count())
@@ -0,0 +1,28 @@
+function sum_from(numbers, i)
+    if (i>(#numbers)) then
+        return 0
+    end
+    return (numbers[i]+sum_from(numbers, (i+1)))
+end
+function sum(numbers)
+    local total = 0
+    if ((#numbers)>0) then
+        total = (numbers[1]+sum_from(numbers, 2))
+    end
+    return total
+end
+function points()
+    local ps = {{ x = 1, y = 2 }, { x = 3, y = 4 }}
+    ps[2].y = (ps[1].x+9)
+    ps[3] = { x = 0, y = 0 }
+    return ps
+end
+function grid()
+    local empty = {}
+    local g = {{"a", "b"}, empty}
+    g[2] = {"c"}
+    return g
+end
+function count()
+    return (((#{1, 2, 3})+(#"four"))+({5, 6})[2])
+end
@@ -0,0 +1,3 @@
+print(classify(3))
+print(classify(30))
+print(count({1, 2}))
@@ -0,0 +1,23 @@
+function classify(n)
+    local label = "small"
+    if (n>10) then
+        local label = "big"
+        print(label)
+    end
+    do
+        local label = "inner"
+        print(label)
+        local n = 0
+    end
+    local label = (label .. "!")
+    return label
+end
+function count(xs)
+    local total = 0
+    for _, x in ipairs(xs) do
+        local total = x
+    end
+    do
+        return (total+1)
+    end
+end
@@ -0,0 +1,58 @@
+function inc(n)
+    return (n+1)
+end
+function apply_twice(f, x)
+    return f(f(x))
+end
+function counter()
+    local count = 0
+    return function()
+        count = (count+1)
+        return count
+    end
+end
+function adder(n)
+    return function(x)
+        return (x+n)
+    end
+end
+function main()
+    print(tostring(apply_twice(inc, 1)))
+    print(tostring(apply_twice(function(x)
+        return (x*x)
+    end, 3)))
+    local next = counter()
+    next()
+    next()
+    print(("counted to " .. next()))
+    local handlers = {inc, adder(10), function(x)
+        for i = 0, 3 - 1 do
+            do
+                if (i==1) then
+                    goto continue_1
+                end
+                x = (x*2)
+            end
+            ::continue_1::
+        end
+        return x
+    end}
+    for _, handler in ipairs(handlers) do
+        print(tostring(handler(1)))
+    end
+    print(tostring(handlers[2](5)))
+    print(tostring(adder(1)(2)))
+    ;(function(message)
+        print(message)
+    end)("called at once")
+    local clicks = 0
+    local button = { label = "ok", on_click = function(label)
+        clicks = (clicks+1)
+        print(("clicked " .. label))
+    end }
+    button.on_click(button.label)
+    button.on_click(button.label)
+    print(("clicks: " .. clicks))
+    local maybe = nil
+    print(tostring((maybe or inc)(41)))
+end
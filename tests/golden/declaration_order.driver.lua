@@ -0,0 +1,2 @@
+print(is_even(10), is_odd(7), is_even(3))
+load()
@@ -0,0 +1,29 @@
+local Counter = {}
+function Counter.load()
+    print(describe(make_team()))
+end
+_G.load = Counter.load
+function is_even(n)
+    if (n==0) then
+        return true
+    end
+    return is_odd((n-1))
+end
+function is_odd(n)
+    if (n==0) then
+        return false
+    end
+    return is_even((n-1))
+end
+function describe(team)
+    return (team.lead.name .. (" leads " .. ((#team.members) .. (" " .. parity((#team.members))))))
+end
+function parity(n)
+    if is_even(n) then
+        return "(even)"
+    end
+    return "(odd)"
+end
+function make_team()
+    return { lead = { name = "Ada" }, members = {{ name = "Bob" }, { name = "Cy" }, { name = "Di" }} }
+end
@@ -0,0 +1,81 @@
+function Circle(r)
+    return { tag = "Circle", r = r }
+end
+function Rect(w, h)
+    return { tag = "Rect", w = w, h = h }
+end
+Empty = { tag = "Empty" }
+Red = { tag = "Red" }
+Yellow = { tag = "Yellow" }
+function Green(seconds)
+    return { tag = "Green", seconds = seconds }
+end
+function area(shape)
+    if shape.tag == "Circle" then
+        local r = shape.r
+        return ((3*r)*r)
+    elseif shape.tag == "Rect" then
+        local w, h = shape.w, shape.h
+        return (w*h)
+    elseif shape.tag == "Empty" then
+        return 0
+    end
+end
+function next(light)
+    if light.tag == "Green" then
+        local seconds = light.seconds
+        if (seconds>0) then
+            return Green((seconds-1))
+        end
+        return Yellow
+    elseif light.tag == "Yellow" then
+        return Red
+    else
+        return Green(2)
+    end
+end
+function describe(light)
+    if light.tag == "Red" then
+        return "red"
+    elseif light.tag == "Yellow" then
+        return "yellow"
+    elseif light.tag == "Green" then
+        local seconds = light.seconds
+        return ("green for " .. seconds)
+    end
+end
+function widest(shapes)
+    local widest = 0
+    for _, shape in ipairs(shapes) do
+        do
+            if shape.tag == "Rect" then
+                local w = shape.w
+                if (w>widest) then
+                    widest = w
+                end
+            else
+                goto continue_1
+            end
+        end
+        ::continue_1::
+    end
+    return widest
+end
+function main()
+    print((tostring(area(Circle(2))) .. (" " .. (area(Rect(2, 5)) .. (" " .. area(Empty))))))
+    local light = Red
+    for i = 0, 5 - 1 do
+        light = next(light)
+        print(describe(light))
+    end
+    local match_ = "stop"
+    do
+        local match = next(Yellow)
+        if match.tag == "Red" then
+            print(match_)
+        else
+            print("go")
+        end
+    end
+    print(tostring(widest({Circle(9), Rect(3, 1), Empty, Rect(4, 2)})))
+end
@@ -0,0 +1,11 @@
+local sin = math.sin
+local floor = math.floor
+local upper = string.upper
+local format_number = string.format
+function shout(s)
+    return (upper(s) .. "!")
+end
+function main()
+    print((shout("hello") .. (" " .. type(1))))
+    print(format_number("%.2f", sin(floor(3.7))))
+end
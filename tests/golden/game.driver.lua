@@ -0,0 +1,5 @@
+load()
+update(0.5)
+keypressed("space")
+keypressed("escape")
+draw()
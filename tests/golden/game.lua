@@ -0,0 +1,24 @@
+local log = print
+function start()
+    return { x = 0, speed = 10 }
+end
+local Pong = {}
+function Pong.load()
+    log("loading")
+end
+function Pong.update(dt)
+    log(("update " .. dt))
+end
+function Pong.keypressed(key)
+    if (key=="escape") then
+        log("bye")
+    end
+end
+function Pong.draw()
+    local ball = start()
+    log(("ball at " .. ball.x))
+end
+_G.load = Pong.load
+_G.update = Pong.update
+_G.keypressed = Pong.keypressed
+_G.draw = Pong.draw
@@ -0,0 +1,76 @@
+function first(xs)
+    if ((#xs)==0) then
+        return nil
+    end
+    return xs[1]
+end
+function map(xs, f)
+    local out = {}
+    for _, x in ipairs(xs) do
+        out[((#out)+1)] = f(x)
+    end
+    return out
+end
+function filter(xs, keep)
+    local out = {}
+    for _, x in ipairs(xs) do
+        if keep(x) then
+            out[((#out)+1)] = x
+        end
+    end
+    return out
+end
+function pair(a, b)
+    return { first = a, second = b }
+end
+function swap(p)
+    return { first = p.second, second = p.first }
+end
+function empty()
+    return { items = {}, size = 0 }
+end
+function push(stack, item)
+    stack.size = (stack.size+1)
+    stack.items[stack.size] = item
+end
+function pop(stack)
+    if (stack.size==0) then
+        return nil
+    end
+    local item = stack.items[stack.size]
+    stack.size = (stack.size-1)
+    return item
+end
+function identity(x)
+    return x
+end
+function main()
+    local numbers = {1, 2, 3, 4}
+    local words = map(numbers, function(n)
+        return ("#" .. n)
+    end)
+    print((first(words) or "none"))
+    local empty_words = {}
+    print((first(empty_words) or "none"))
+    local one = first({1, 2})
+    local nested = (first({{3}, {4}}) or {0})
+    print((tostring(nested[1]) .. (" " .. (one or 0))))
+    local evens = filter(numbers, function(n)
+        return ((n%2)==0)
+    end)
+    print((tostring((#evens)) .. " evens"))
+    local p = pair("answer", 42)
+    local q = swap(p)
+    print((q.second .. (" = " .. q.first)))
+    local origin = { first = 0, second = 0 }
+    print(tostring(swap(origin).first))
+    local stack = empty()
+    push(stack, "a")
+    push(stack, "b")
+    print((pop(stack) or "empty"))
+    print((pop(stack) or "empty"))
+    print((pop(stack) or "empty"))
+    local same = identity
+    print(tostring(same(7)))
+    print(identity("generic"))
+end
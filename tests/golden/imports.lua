@@ -0,0 +1,29 @@
+local math_abs = math.abs
+local math_ceil = math.ceil
+local math_floor = math.floor
+local math_sqrt = math.sqrt
+local math_exp = math.exp
+local math_log = math.log
+local math_sin = math.sin
+local math_cos = math.cos
+local math_tan = math.tan
+local math_fmod = math.fmod
+local math_min = math.min
+local math_max = math.max
+local math_random = math.random
+local string_len = string.len
+local string_lower = string.lower
+local string_upper = string.upper
+local string_rep = string.rep
+local string_reverse = string.reverse
+local string_sub = string.sub
+local string_format = string.format
+local string_find = string.find
+function hypot(a, b)
+    return math_sqrt(((a*a)+(b*b)))
+end
+function main()
+    print(string_format("%.1f", hypot(3, 4)))
+    print(string_rep(string_upper("ab"), 3))
+    print(tostring(math_max(math_floor(2.5), math_abs((-7)))))
+end
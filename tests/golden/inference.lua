@@ -0,0 +1,38 @@
+function Circle(r)
+    return { tag = "Circle", r = r }
+end
+Empty = { tag = "Empty" }
+function first(xs)
+    return xs[1]
+end
+function origin()
+    return { x = 0, y = 0 }
+end
+function main()
+    local sum = (1+2)
+    local name = "phobos"
+    local done = ((sum>2) and true)
+    local numbers = {sum, 4, 5}
+    local p = origin()
+    p.x = numbers[2]
+    local maybe = first(numbers)
+    local shape = Circle(2)
+    local inc = function(n)
+        return (n+1)
+    end
+    local explicit = sum
+    local total = 0
+    for _, n in ipairs(numbers) do
+        total = (total+n)
+    end
+    if done then
+        print((name .. (" " .. total)))
+    end
+    print((tostring(inc(p.x)) .. (" " .. ((maybe or 0) .. (" " .. (explicit or 0))))))
+    if shape.tag == "Circle" then
+        local r = shape.r
+        print(("circle " .. r))
+    elseif shape.tag == "Empty" then
+        print("empty")
+    end
+end
@@ -0,0 +1,55 @@
+local Player = {}
+Player.__index = Player
+local Wall = {}
+Wall.__index = Wall
+function Player.draw(self)
+    print(("player " .. (self.nickname .. (" at " .. (self.x .. (", " .. self.y))))))
+end
+function Player.area(self)
+    return 1
+end
+function Player.name(self)
+    return self.nickname
+end
+function Wall.same(self, other)
+    return ((self.w==other.w) and (self.h==other.h))
+end
+function Wall.draw(self)
+    print(("wall " .. (self.w .. ("x" .. self.h))))
+end
+function Wall.area(self)
+    return (self.w*self.h)
+end
+function Wall.perimeter(self)
+    return (2*(self.w+self.h))
+end
+function draw_all(things)
+    local total = 0
+    for _, thing in ipairs(things) do
+        thing:draw()
+        total = (total+thing:area())
+    end
+    return total
+end
+function greet(who)
+    print(("hello, " .. who:name()))
+end
+function same_twice(a, b)
+    return (a:same(b) and b:same(a))
+end
+function main()
+    local p = setmetatable({ nickname = "ada", x = 1, y = 2 }, Player)
+    local w = setmetatable({ w = 3, h = 4 }, Wall)
+    local things = {p, w}
+    print(("total area " .. draw_all(things)))
+    greet(p)
+    p:draw()
+    print(("wall area " .. (w:area() .. (", perimeter " .. w:perimeter()))))
+    if (w:same(setmetatable({ w = 3, h = 4 }, Wall)) and same_twice(w, w)) then
+        print("the walls are the same")
+    end
+    local maybe = w
+    if (maybe~=nil) then
+        maybe:draw()
+    end
+end
@@ -0,0 +1,4 @@
+print(sum_to(5), sum_to(0))
+print(sum_odd({1, 2, 3, 4, 5}))
+print(first_over({1, 5, 10, 20}, 7), first_over({1}, 7))
+print(all_pairs(3))
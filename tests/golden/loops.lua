@@ -0,0 +1,55 @@
+function sum_to(n)
+    local total = 0
+    for i = 0, n - 1 do
+        total = (total+i)
+    end
+    return total
+end
+function sum_odd(numbers)
+    local total = 0
+    for _, x in ipairs(numbers) do
+        do
+            if ((x%2)==0) then
+                goto continue_1
+            end
+            local odd = x
+            total = (total+odd)
+        end
+        ::continue_1::
+    end
+    return total
+end
+function first_over(numbers, limit)
+    local i = 1
+    local found = (-1)
+    while (i<=(#numbers)) do
+        if (numbers[i]>limit) then
+            found = numbers[i]
+            break
+        end
+        i = (i+1)
+    end
+    return found
+end
+function all_pairs(n)
+    local text = ""
+    for a = 1, n do
+        do
+            for b = 1, n do
+                do
+                    if (a==b) then
+                        goto continue_2
+                    end
+                    text = (text .. (a .. (b .. " ")))
+                end
+                ::continue_2::
+            end
+            if (a==2) then
+                goto continue_1
+            end
+            text = (text .. "| ")
+        end
+        ::continue_1::
+    end
+    return text
+end
@@ -0,0 +1 @@
+love.draw()
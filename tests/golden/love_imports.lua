@@ -0,0 +1,22 @@
+local timer_getTime = love.timer.getTime
+local timer_getDelta = love.timer.getDelta
+local timer_getFPS = love.timer.getFPS
+local graphics_clear = love.graphics.clear
+local graphics_setColor = love.graphics.setColor
+local graphics_setBackgroundColor = love.graphics.setBackgroundColor
+local graphics_rectangle = love.graphics.rectangle
+local graphics_circle = love.graphics.circle
+local graphics_line = love.graphics.line
+local graphics_print = love.graphics.print
+local graphics_getWidth = love.graphics.getWidth
+local graphics_getHeight = love.graphics.getHeight
+local keyboard_isDown = love.keyboard.isDown
+local Demo = {}
+function Demo.draw()
+    if keyboard_isDown("space") then
+        graphics_setColor(1, 0, 0, 1)
+    end
+    graphics_rectangle("fill", 10, 10, (graphics_getWidth()/2), 20)
+    graphics_print("hello", 0, 0)
+end
+love.draw = Demo.draw
@@ -0,0 +1,2 @@
+main()
+print(_G.string.rep("-", 3))
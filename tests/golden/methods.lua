@@ -0,0 +1,65 @@
+local sqrt = math.sqrt
+local Point = {}
+Point.__index = Point
+local Segment = {}
+Segment.__index = Segment
+local Stack = {}
+Stack.__index = Stack
+local string = {}
+string.__index = string
+function Point.length(self)
+    return sqrt(((self.x*self.x)+(self.y*self.y)))
+end
+function Point.plus(self, other)
+    return setmetatable({ x = (self.x+other.x), y = (self.y+other.y) }, Point)
+end
+function Point.scaled(self, k)
+    return setmetatable({ x = (self.x*k), y = (self.y*k) }, Point)
+end
+function Point.end_(self)
+    return ("(" .. (self.x .. (", " .. (self.y .. ")"))))
+end
+function Segment.length(self)
+    return self.to:plus(self.from:scaled((-1))):length()
+end
+function Stack.push(self, item)
+    self.size = (self.size+1)
+    self.items[self.size] = item
+end
+function Stack.pop(self)
+    if (self.size==0) then
+        return nil
+    end
+    local item = self.items[self.size]
+    self.size = (self.size-1)
+    return item
+end
+function Stack.is_empty(self)
+    return (self.size==0)
+end
+function string.shout(self)
+    return (self.text .. "!")
+end
+function origin()
+    return setmetatable({ x = 0, y = 0 }, Point)
+end
+function main()
+    local p = setmetatable({ x = 3, y = 4 }, Point)
+    print(tostring(p:length()))
+    print(p:plus(p):scaled(2):end_())
+    print(origin():plus(p):end_())
+    local s = setmetatable({ from = origin(), to = p:scaled(2) }, Segment)
+    print(tostring(s:length()))
+    local stack = setmetatable({ items = {}, size = 0 }, Stack)
+    stack:push("a")
+    stack:push("b")
+    while (not stack:is_empty()) do
+        print((stack:pop() or "none"))
+    end
+    local points = {p, setmetatable({ x = 1, y = 0 }, Point)}
+    for _, q in ipairs(points) do
+        print(q:end_())
+    end
+    local loud = setmetatable({ text = "hey" }, string)
+    print(loud:shout())
+end
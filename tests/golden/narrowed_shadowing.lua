@@ -0,0 +1,13 @@
+function describe(score)
+    if (score~=nil) then
+        local score = (score*2)
+        return ("doubled to " .. score)
+    else
+        local score = 0
+        return ("defaulted to " .. score)
+    end
+end
+function main()
+    print(describe(4))
+    print(describe(nil))
+end
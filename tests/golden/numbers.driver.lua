@@ -0,0 +1,3 @@
+print(add_one(1))
+print(add_one_point_zero(1))
+print(add_one_point_five(1))
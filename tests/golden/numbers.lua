@@ -0,0 +1,9 @@
+function add_one(x)
+    return (x+1)
+end
+function add_one_point_zero(x)
+    return (x+1)
+end
+function add_one_point_five(x)
+    return (x+1.5)
+end
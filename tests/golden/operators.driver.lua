@@ -0,0 +1,6 @@
+print(clamp(-3, 0, 10), clamp(30, 0, 10), clamp(4, 0, 10))
+print(between(3, 0, 10), between(11, 0, 10))
+print(describe(5))
+print(describe(20))
+print(describe(3))
+print(arithmetic(3))
@@ -0,0 +1,23 @@
+function clamp(x, lo, hi)
+    if (x<lo) then
+        return lo
+    elseif (x>hi) then
+        return hi
+    end
+    return x
+end
+function between(x, lo, hi)
+    return ((x>=lo) and (x<=hi))
+end
+function describe(x)
+    local text = "x"
+    if ((not between(x, 0, 10)) or (x==5)) then
+        text = (text .. " is special")
+    else
+        text = (text .. (" = " .. x))
+    end
+    return text
+end
+function arithmetic(x)
+    return (((-(x^2))+(7%4))-(-1))
+end
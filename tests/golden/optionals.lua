@@ -0,0 +1,71 @@
+local string_len = string.len
+local string_lower = string.lower
+local string_upper = string.upper
+local string_rep = string.rep
+local string_reverse = string.reverse
+local string_sub = string.sub
+local string_format = string.format
+local string_find = string.find
+function describe(player)
+    if (player==nil) then
+        return "nobody"
+    else
+        local score = player.score
+        if (score~=nil) then
+            return (player.name .. (" has " .. score))
+        end
+        return (player.name .. " has no score")
+    end
+end
+function parse(input)
+    return (tonumber(input) or (-1))
+end
+function or_true(flag)
+    return ((flag ~= nil and flag) or (flag == nil and true))
+end
+function flag_of(input)
+    if (input=="yes") then
+        return true
+    elseif (input=="no") then
+        return false
+    end
+    return nil
+end
+function either(value, default)
+    return ((value ~= nil and value) or (value == nil and default))
+end
+function position(s)
+    local at = string_find(s, "b")
+    if (at==nil) then
+        return "missing"
+    end
+    return ("at " .. (at or 0))
+end
+function main()
+    local nobody = nil
+    print(describe(nobody))
+    print(describe({ name = "ann", score = 3 }))
+    print(describe({ name = "bob", score = nil }))
+    print((tostring(parse("42")) .. (" " .. parse("x"))))
+    if or_true(false) then
+        print("wrong")
+    else
+        print("false is kept")
+    end
+    if ((function(...) if ... == nil then return true end return ... end)(flag_of("no"))) then
+        print("wrong")
+    end
+    local value = "shadowed"
+    if ((function(...) if ... == nil then return (value=="shadowed") end return ... end)(flag_of("maybe"))) then
+        print("the default sees its own variables")
+    end
+    if ((not either(false, true)) and either(nil, true)) then
+        print("false is kept by generic functions too")
+    end
+    print((position("abc") .. (", " .. position("xyz"))))
+    local maybe = nil
+    maybe = 7
+    if (maybe==7) then
+        print("seven")
+    end
+end
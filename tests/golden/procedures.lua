@@ -0,0 +1,22 @@
+local log = print
+function greet(name)
+    if (name=="") then
+        return
+    end
+    log(("hello " .. name))
+end
+function count_to(n)
+    for i = 1, n do
+        if (i>3) then
+            return
+        end
+        log(("" .. i))
+    end
+end
+local Demo = {}
+function Demo.load()
+    greet("")
+    greet("world")
+    count_to(10)
+end
+_G.load = Demo.load
@@ -0,0 +1,3 @@
+local c = pair()
+print(describe(c))
+print(older(c.first).age, c.second["end"])
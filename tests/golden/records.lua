@@ -0,0 +1,15 @@
+function make_person(name, age)
+    return { name = name, age = age, ["end"] = false }
+end
+function older(p)
+    p.age = (p.age+1)
+    return p
+end
+function describe(c)
+    return (c.first.name .. (" (" .. (c.first.age .. (") and " .. c.second.name))))
+end
+function pair()
+    local c = { second = make_person("June", 40), first = { name = "Johnny", age = 42, ["end"] = true } }
+    c.second["end"] = c.first["end"]
+    return c
+end
@@ -0,0 +1,2 @@
+print(factorial(0))
+print(factorial(5))
@@ -0,0 +1 @@
+print(sign(-4), sign(0), sign(9), first({7}))
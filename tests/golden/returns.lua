@@ -0,0 +1,17 @@
+function sign(n)
+    if (n<0) then
+        return (-1)
+    elseif (n>0) then
+        return 1
+    else
+        return 0
+    end
+end
+function first(xs)
+    do
+        return xs[1]
+    end
+end
+function log(message)
+    print(message)
+end
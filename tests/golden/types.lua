@@ -0,0 +1,3 @@
+function f(p)
+    return "I get the point"
+end